@@ -0,0 +1,234 @@
+use crate::ailments::BenchAilment;
+use crate::battle::{BattlePokemon, DamageContext};
+use crate::hooks::{
+    DamageHook, HookKey, StatHook, StatusHook, TRIGGERS, Trigger,
+    TriggerContext, TriggerHook};
+use rand::Rng;
+use vdex::Ability;
+use vdex::Enum;
+use vdex::moves::{self, DamageClass};
+use vdex::Stat;
+use vdex::Type;
+
+fn physical(ctx: &DamageContext) -> bool {
+    if let DamageClass::Physical = ctx.class { true } else { false }
+}
+
+pub fn status_immunity(ability: Ability) -> Option<StatusHook> {
+    match ability {
+        Ability::Limber => Some(StatusHook(|_, ailment| {
+            if let BenchAilment::Paralyzed = ailment { true } else { false }
+        })),
+        Ability::Insomnia | Ability::VitalSpirit => Some(StatusHook(|_, ailment| {
+            if let BenchAilment::Asleep { .. } = ailment { true } else { false }
+        })),
+        Ability::MagmaArmor => Some(StatusHook(|_, ailment| {
+            if let BenchAilment::Frozen = ailment { true } else { false }
+        })),
+        Ability::WaterVeil => Some(StatusHook(|_, ailment| {
+            if let BenchAilment::Burned = ailment { true } else { false }
+        })),
+        Ability::Immunity => Some(StatusHook(|_, ailment| {
+            if let BenchAilment::Poisoned { .. } = ailment { true } else { false }
+        })),
+        _ => None,
+    }
+}
+
+pub fn stat_drop_block(ability: Ability) -> Option<StatHook> {
+    match ability {
+        Ability::ClearBody | Ability::WhiteSmoke
+            => Some(StatHook(|_, _| true)),
+        Ability::HyperCutter
+            => Some(StatHook(|_, stat| stat == Stat::Attack)),
+        Ability::KeenEye
+            => Some(StatHook(|_, stat| stat == Stat::Accuracy)),
+        _ => None,
+    }
+}
+
+pub fn power_modifier(ability: Ability) -> Option<DamageHook> {
+    match ability {
+        Ability::Technician => Some(DamageHook(|ctx, _, _| {
+            if ctx.power <= 60 { 1.5 } else { 1.0 }
+        })),
+        _ => None,
+    }
+}
+
+pub fn attack_modifier(ability: Ability) -> Option<DamageHook> {
+    match ability {
+        Ability::HugePower | Ability::PurePower
+            => Some(DamageHook(|ctx, _, _| {
+                if physical(ctx) { 2.0 } else { 1.0 }
+            })),
+        Ability::Hustle => Some(DamageHook(|ctx, _, _| {
+            if physical(ctx) { 1.5 } else { 1.0 }
+        })),
+        _ => None,
+    }
+}
+
+pub fn user_accuracy_modifier(ability: Ability) -> Option<DamageHook> {
+    match ability {
+        Ability::Hustle => Some(DamageHook(|ctx, _, _| {
+            if physical(ctx) { 0.8 } else { 1.0 }
+        })),
+        Ability::CompoundEyes => Some(DamageHook(|_, _, _| 1.3)),
+        _ => None,
+    }
+}
+
+pub fn user_damage_modifier(ability: Ability) -> Option<DamageHook> {
+    match ability {
+        Ability::Sniper => Some(DamageHook(|ctx, _, target| {
+            if ctx.critical_against(target) { 1.5 } else { 1.0 }
+        })),
+        Ability::TintedLens => Some(DamageHook(|ctx, _, target| {
+            if ctx.effectiveness_against(target) < 1.0 { 2.0 } else { 1.0 }
+        })),
+        _ => None,
+    }
+}
+
+pub fn target_damage_modifier(ability: Ability) -> Option<DamageHook> {
+    match ability {
+        Ability::ThickFat => Some(DamageHook(|ctx, _, _| match ctx.typ {
+            Some(Type::Fire) | Some(Type::Ice) => 0.5,
+            _ => 1.0,
+        })),
+        Ability::Filter | Ability::SolidRock
+            => Some(DamageHook(|ctx, _, target| {
+                if ctx.effectiveness_against(target) > 1.0 { 0.75 } else { 1.0 }
+            })),
+        _ => None,
+    }
+}
+
+pub fn cancels_criticals(ability: Ability) -> bool {
+    match ability {
+        Ability::BattleArmor | Ability::ShellArmor => true,
+        _ => false,
+    }
+}
+
+fn stat_change(stat: Stat, change: i8) -> [i8; moves::CHANGEABLE_STATS] {
+    let mut changes = [0; moves::CHANGEABLE_STATS];
+    changes[stat.repr() as usize] = change;
+    changes
+}
+
+fn intimidate(ctx: &mut TriggerContext) {
+    if let Some(foe) = ctx.other {
+        if !foe.borrow().is_fainted() {
+            foe.borrow_mut().change_stats_by_foe(stat_change(Stat::Attack, -1));
+        }
+    }
+}
+
+fn rough_skin(ctx: &mut TriggerContext) {
+    if let Some(attacker) = ctx.other {
+        let mut attacker = attacker.borrow_mut();
+        let guarded = attacker.overlay.ability == Ability::MagicGuard;
+        if !attacker.is_fainted() && !guarded {
+            let max_hp = attacker.overlay.stat(Stat::HP);
+            attacker.direct_damage((max_hp / 8).max(1));
+        }
+    }
+}
+
+fn static_paralysis(ctx: &mut TriggerContext) {
+    if let Some(attacker) = ctx.other {
+        if ctx.rng.gen_range(0, 10) < 3 && !attacker.borrow().is_fainted() {
+            attacker.borrow_mut().inflict(BenchAilment::Paralyzed);
+        }
+    }
+}
+
+fn speed_boost(ctx: &mut TriggerContext) {
+    ctx.current.borrow_mut().change_stats(stat_change(Stat::Speed, 1));
+}
+
+fn natural_cure(ctx: &mut TriggerContext) {
+    ctx.current.borrow_mut().cure();
+}
+
+fn synchronize(ctx: &mut TriggerContext) {
+    // Before Generation V, bad poison is passed back as regular poison.
+    let status = match ctx.status {
+        Some(status @ BenchAilment::Burned)
+            | Some(status @ BenchAilment::Paralyzed) => status,
+        Some(BenchAilment::Poisoned { .. })
+            => BenchAilment::Poisoned { bad: false },
+        _ => return,
+    };
+    if let Some(source) = ctx.other {
+        if !source.ptr_eq(ctx.current) {
+            source.borrow_mut().inflict(status);
+        }
+    }
+}
+
+fn shed_skin(ctx: &mut TriggerContext) {
+    let afflicted = !ctx.current.borrow().perm.borrow().status.is_none();
+    if afflicted && ctx.rng.gen_range(0, 10) < 3 {
+        ctx.current.borrow_mut().cure();
+    }
+}
+
+pub fn trigger(ability: Ability, trigger: Trigger) -> Option<TriggerHook> {
+    match (ability, trigger) {
+        (Ability::Intimidate, Trigger::OnSwitchIn)
+            => Some(TriggerHook(intimidate)),
+        (Ability::RoughSkin, Trigger::OnContact)
+            => Some(TriggerHook(rough_skin)),
+        (Ability::Static, Trigger::OnContact)
+            => Some(TriggerHook(static_paralysis)),
+        (Ability::SpeedBoost, Trigger::EndOfTurn)
+            => Some(TriggerHook(speed_boost)),
+        (Ability::NaturalCure, Trigger::OnSwitchOut)
+            => Some(TriggerHook(natural_cure)),
+        (Ability::Synchronize, Trigger::OnStatusInflicted)
+            => Some(TriggerHook(synchronize)),
+        (Ability::ShedSkin, Trigger::EndOfTurn)
+            => Some(TriggerHook(shed_skin)),
+        _ => None,
+    }
+}
+
+pub fn install(pokemon: &mut BattlePokemon) {
+    // Overlay hooks are cleared by `BattlePokemon::teardown` when the Pokémon
+    // leaves the field.
+    let ability = pokemon.overlay.ability;
+    let key = HookKey::new_ability(0, ability, 0);
+    let hooks = &mut pokemon.hooks;
+    if let Some(hook) = status_immunity(ability) {
+        hooks.status_immunities.overlay.insert(key, hook);
+    }
+    if let Some(hook) = stat_drop_block(ability) {
+        hooks.stat_drop_blocks.overlay.insert(key, hook);
+    }
+    if let Some(hook) = power_modifier(ability) {
+        hooks.power_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = attack_modifier(ability) {
+        hooks.attack_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = user_accuracy_modifier(ability) {
+        hooks.user_accuracy_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = user_damage_modifier(ability) {
+        hooks.user_damage_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = target_damage_modifier(ability) {
+        hooks.target_damage_modifiers.overlay.insert(key, hook);
+    }
+    if cancels_criticals(ability) {
+        hooks.critical_cancels.overlay.insert(key, true);
+    }
+    for kind in TRIGGERS.iter() {
+        if let Some(hook) = trigger(ability, *kind) {
+            hooks.triggers_mut(*kind).overlay.insert(key, hook);
+        }
+    }
+}
@@ -16,6 +16,16 @@ impl Default for BenchAilment {
     fn default() -> Self { BenchAilment::None }
 }
 
+impl BenchAilment {
+    pub fn is_none(&self) -> bool {
+        if let BenchAilment::None = self {
+            true
+        } else {
+            false
+        }
+    }
+}
+
 bitflags! {
     #[derive(Default)]
     pub struct BattlerAilmentFlags: u32 {
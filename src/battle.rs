@@ -1,34 +1,43 @@
+use crate::abilities;
 use crate::ailments;
-use crate::hooks::Hooks;
+use crate::events::{Event, Log};
+use crate::exec::moves::STRUGGLE;
+use crate::hooks::{Hooks, Trigger, TriggerContext};
+use crate::field::{Field, HAZARDS, Hazard, SideCondition, Weather};
 use crate::formats::AbsoluteTarget;
+use crate::items::{self, HeldItem};
+use crate::shared::Shared;
 use crate::team::TeamMember;
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::sync::Arc;
 use vdex::Enum;
 use vdex::moves;
 use vdex::pokemon::OneOrTwo;
 use vdex::Stat;
 use vdex::Type;
 
-pub type Benched = Rc<RefCell<BenchPokemon>>;
+pub type Benched = Shared<BenchPokemon>;
 pub type Bench = Vec<Benched>;
-pub type Current = Rc<RefCell<BattlePokemon>>;
+pub type Current = Shared<BattlePokemon>;
 
 #[derive(Clone, Debug)]
 pub struct BenchPokemon {
-    pub base: Rc<TeamMember>,
+    pub base: Arc<TeamMember>,
     pub status: ailments::BenchAilment,
     pub hp: u16,
     pub pp: [u8; 4],
+    pub held: Option<&'static vdex::items::Item>,
+    pub consumed: Option<&'static vdex::items::Item>,
 }
 
 impl BenchPokemon {
-    pub fn new(base: &Rc<TeamMember>) -> Self {
+    pub fn new(base: &Arc<TeamMember>) -> Self {
         Self {
             base: base.clone(),
             status: Default::default(),
             hp: base.stat(Stat::HP),
             pp: [base.max_pp(0), base.max_pp(1), base.max_pp(2), base.max_pp(3)],
+            held: base.held,
+            consumed: None,
         }
     }
 }
@@ -37,47 +46,364 @@ impl BenchPokemon {
 pub struct BattlePokemon {
     pub position: AbsoluteTarget,
     pub index: usize,
+    pub team: Bench,
     pub perm: Benched,
     pub hooks: Hooks,
+    pub log: Log,
+    pub field: Shared<Field>,
     pub overlay: TeamMember,
     pub types: OneOrTwo<Type>,
     pub status: ailments::BattlerAilments,
     pub stat_changes: [i8; moves::CHANGEABLE_STATS],
     pub critical_rate: i8,
+    pub choice_lock: Option<u8>,
+    pub destiny_bond: bool,
+    pub dealt_damage: bool,
+    pub moved: bool,
+    pub flinched: bool,
 }
 
 impl BattlePokemon {
     pub fn new(
-        position: AbsoluteTarget, index: usize, perm: &Benched, hooks: &Hooks
+        position: AbsoluteTarget, team: &Bench, index: usize, hooks: &Hooks,
+        log: &Log, field: &Shared<Field>
     ) -> Self {
-        Self {
+        let perm = &team[index];
+        let mut pokemon = Self {
             position,
             index,
+            team: team.clone(),
             perm: perm.clone(),
             hooks: Hooks::new_overlay(hooks),
+            log: log.clone(),
+            field: field.clone(),
             overlay: (*perm.borrow().base).clone(),
             types: perm.borrow().base.pokemon.types,
             status: Default::default(),
             stat_changes: [0; moves::CHANGEABLE_STATS],
             critical_rate: 0,
+            choice_lock: None,
+            destiny_bond: false,
+            dealt_damage: false,
+            moved: false,
+            flinched: false,
+        };
+        abilities::install(&mut pokemon);
+        items::install(&mut pokemon);
+        pokemon
+    }
+
+    pub fn switch_in(&mut self) {
+        for hazard in HAZARDS.iter() {
+            if !self.is_fainted() {
+                self.apply_hazard(*hazard);
+            }
+        }
+        if self.is_fainted() {
+            return;
+        }
+        if let Some(weather) = Weather::from_ability(self.overlay.ability) {
+            self.set_weather(weather, None);
         }
     }
 
-    pub fn direct_damage(&mut self, amt: u16) -> u16 {
-        let mut perm = self.perm.borrow_mut();
-        let capped = amt.min(perm.hp);
-        perm.hp -= capped;
+    pub fn is_grounded(&self) -> bool {
+        let flags = self.status.flags;
+        if flags.intersects(ailments::BattlerAilmentFlags::ROOTED
+            | ailments::BattlerAilmentFlags::SMACKED_DOWN)
+        {
+            return true;
+        }
+        !(self.types.contains(Type::Flying)
+            || self.overlay.ability == vdex::Ability::Levitate
+            || flags.intersects(ailments::BattlerAilmentFlags::LEVITATING
+                | ailments::BattlerAilmentFlags::TELEKINESIS))
+    }
+
+    fn apply_hazard(&mut self, hazard: Hazard) {
+        let side = self.side();
+        let layers = self.field.borrow().layers(side, hazard);
+        let airborne = hazard != Hazard::StealthRock && !self.is_grounded();
+        if layers == 0 || airborne {
+            return;
+        }
+        if hazard == Hazard::ToxicSpikes && self.types.contains(Type::Poison) {
+            self.field.borrow_mut().hazards[side][hazard as usize] = 0;
+            self.log(Event::HazardRemoved { side, hazard });
+            return;
+        }
+        self.log(Event::Hazard { target: self.position, hazard });
+        let max_hp = self.overlay.stat(Stat::HP) as f64;
+        let fraction = match hazard {
+            Hazard::Spikes => match layers {
+                1 => 1.0 / 8.0,
+                2 => 1.0 / 6.0,
+                _ => 1.0 / 4.0,
+            },
+            Hazard::StealthRock => self.efficacy(Type::Rock) / 8.0,
+            Hazard::ToxicSpikes => {
+                self.inflict(ailments::BenchAilment::Poisoned {
+                    bad: layers > 1,
+                });
+                return;
+            },
+        };
+        if self.overlay.ability != vdex::Ability::MagicGuard {
+            self.direct_damage(((max_hp * fraction) as u16).max(1));
+        }
+    }
+
+    pub fn set_hazard(&self, side: usize, hazard: Hazard) -> bool {
+        let layers = {
+            let mut field = self.field.borrow_mut();
+            let layers = &mut field.hazards[side][hazard as usize];
+            if *layers >= hazard.max_layers() {
+                return false;
+            }
+            *layers += 1;
+            *layers
+        };
+        self.log(Event::HazardSet { side, hazard, layers });
+        true
+    }
+
+    pub fn clear_hazards(&self) {
+        let side = self.side();
+        for hazard in HAZARDS.iter() {
+            let cleared = {
+                let mut field = self.field.borrow_mut();
+                let layers = &mut field.hazards[side][*hazard as usize];
+                let cleared = *layers > 0;
+                *layers = 0;
+                cleared
+            };
+            if cleared {
+                self.log(Event::HazardRemoved { side, hazard: *hazard });
+            }
+        }
+    }
+
+    pub fn set_weather(&self, weather: Weather, turns: Option<u8>) -> bool {
+        let mut field = self.field.borrow_mut();
+        if field.weather == weather {
+            return false;
+        }
+        field.weather = weather;
+        field.weather_turns = turns;
+        self.log(Event::WeatherStarted { weather });
+        true
+    }
+
+    pub fn side(&self) -> usize {
+        self.position.side()
+    }
+
+    pub fn side_has(&self, condition: SideCondition) -> bool {
+        self.field.borrow().has(self.side(), condition)
+    }
+
+    pub fn start_side_condition(
+        &self, condition: SideCondition, mut turns: u8
+    ) -> bool {
+        let side = self.side();
+        if let Some(HeldItem::LightClay) = self.held_item() {
+            match condition {
+                SideCondition::Reflect | SideCondition::LightScreen
+                    => turns = items::LIGHT_CLAY_TURNS,
+                _ => (),
+            }
+        }
+        {
+            let mut field = self.field.borrow_mut();
+            let remaining = field.side_turns(side, condition);
+            if *remaining > 0 {
+                return false;
+            }
+            *remaining = turns;
+        }
+        self.log(Event::SideConditionStarted { side, condition });
+        true
+    }
+
+    pub fn teardown(&mut self) {
+        self.hooks.clear_overlay();
+        self.status = Default::default();
+        self.stat_changes = [0; moves::CHANGEABLE_STATS];
+        self.critical_rate = 0;
+        self.choice_lock = None;
+        self.destiny_bond = false;
+    }
+
+    pub fn held_item(&self) -> Option<HeldItem> {
+        if self.status.flags.contains(ailments::BattlerAilmentFlags::EMBARGOED) {
+            return None;
+        }
+        self.perm.borrow().held.and_then(HeldItem::from_item)
+    }
+
+    fn uninstall_item(&mut self) {
+        if let Some(held) = self.held_item() {
+            self.critical_rate -= held.critical_stages();
+        }
+        self.hooks.clear_item_overlay();
+    }
+
+    pub fn set_held(&mut self, item: Option<&'static vdex::items::Item>) {
+        self.uninstall_item();
+        self.perm.borrow_mut().held = item;
+        self.choice_lock = None;
+        items::install(self);
+    }
+
+    pub fn gain_item(&mut self, item: Option<&'static vdex::items::Item>) {
+        self.set_held(item);
+        if let Some(item) = item {
+            self.log(Event::ItemGained { target: self.position, item: item.id });
+        }
+    }
+
+    pub fn can_lose_item(&self) -> bool {
+        self.overlay.ability != vdex::Ability::StickyHold
+    }
+
+    pub fn embargo(&mut self, turns: u8) -> bool {
+        if self.status.flags.contains(ailments::BattlerAilmentFlags::EMBARGOED) {
+            return false;
+        }
+        self.uninstall_item();
+        self.status.flags.insert(ailments::BattlerAilmentFlags::EMBARGOED);
+        self.status.remaining_embargo_turns = turns;
+        self.log(Event::Embargoed { target: self.position });
+        true
+    }
+
+    pub fn tick_embargo(&mut self) {
+        if !self.status.flags.contains(ailments::BattlerAilmentFlags::EMBARGOED) {
+            return;
+        }
+        let turns = self.status.remaining_embargo_turns.saturating_sub(1);
+        self.status.remaining_embargo_turns = turns;
+        if turns == 0 {
+            self.status.flags.remove(ailments::BattlerAilmentFlags::EMBARGOED);
+            items::install(self);
+            self.log(Event::EmbargoEnded { target: self.position });
+        }
+    }
+
+    pub fn consume_item(&mut self) {
+        let item = self.perm.borrow().held;
+        if let Some(item) = item {
+            self.perm.borrow_mut().consumed = Some(item);
+            self.set_held(None);
+            self.log(Event::ItemConsumed { target: self.position, item: item.id });
+        }
+    }
+
+    pub fn remove_item(&mut self) -> Option<&'static vdex::items::Item> {
+        let item = self.perm.borrow().held;
+        if let Some(item) = item {
+            self.set_held(None);
+            self.log(Event::ItemRemoved { target: self.position, item: item.id });
+        }
+        item
+    }
+
+    pub fn recycle(&mut self) -> bool {
+        let item = {
+            let mut perm = self.perm.borrow_mut();
+            if perm.held.is_some() {
+                return false;
+            }
+            perm.consumed.take()
+        };
+        if item.is_some() {
+            self.gain_item(item);
+            items::check_berry(self);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn cure(&mut self) -> bool {
+        if self.perm.borrow().status.is_none() {
+            return false;
+        }
+        self.perm.borrow_mut().status = ailments::BenchAilment::None;
+        self.status.flags.remove(ailments::BattlerAilmentFlags::NIGHTMARE);
+        self.status.turns_badly_poisoned = 0;
+        self.log(Event::StatusCured { target: self.position });
+        true
+    }
+
+    pub fn cure_confusion(&mut self) -> bool {
+        if !self.is_confused() {
+            return false;
+        }
+        self.status.flags.remove(ailments::BattlerAilmentFlags::CONFUSED);
+        self.log(Event::ConfusionEnded { target: self.position });
+        true
+    }
+
+    pub fn faint(&mut self) {
+        let hp = self.perm.borrow().hp;
+        self.direct_damage(hp);
+    }
+
+    pub fn is_fainted(&self) -> bool {
+        self.perm.borrow().hp == 0
+    }
+
+    pub fn log(&self, event: Event) {
+        self.log.borrow_mut().push(event);
+    }
+
+    pub fn take_damage(
+        &mut self, amt: u16, critical: bool, effectiveness: f64
+    ) -> u16 {
+        let (capped, fainted) = {
+            let mut perm = self.perm.borrow_mut();
+            let capped = amt.min(perm.hp);
+            perm.hp -= capped;
+            (capped, perm.hp == 0)
+        };
+        if capped > 0 {
+            self.log(Event::Damage {
+                target: self.position,
+                amount: capped,
+                critical,
+                effectiveness,
+            });
+            if fainted {
+                self.log(Event::Faint { target: self.position });
+            } else {
+                items::check_berry(self);
+            }
+        }
         capped
     }
 
+    pub fn direct_damage(&mut self, amt: u16) -> u16 {
+        self.take_damage(amt, false, 1.0)
+    }
+
     pub fn direct_heal(&mut self, amt: u16) -> u16 {
-        let mut perm = self.perm.borrow_mut();
-        let capped = amt.min(self.overlay.stat(Stat::HP) - perm.hp);
-        perm.hp += capped;
+        let capped = {
+            let mut perm = self.perm.borrow_mut();
+            let capped = amt.min(self.overlay.stat(Stat::HP) - perm.hp);
+            perm.hp += capped;
+            capped
+        };
+        if capped > 0 {
+            self.log(Event::Heal { target: self.position, amount: capped });
+        }
         capped
     }
 
     pub fn direct_percentage(&mut self, base: u16, percent: i8) -> u16 {
+        if percent == 0 {
+            return 0;
+        }
         let amt = (base.checked_mul(percent.abs() as u16)
             .unwrap_or(std::u16::MAX) / 100).max(1);
         if percent > 0 {
@@ -87,12 +413,102 @@ impl BattlePokemon {
         }
     }
 
-    pub fn change_stats(&mut self, changes: [i8; moves::CHANGEABLE_STATS]) {
+    pub fn can_inflict(&self, ailment: &ailments::BenchAilment) -> bool {
+        if !self.perm.borrow().status.is_none() {
+            return false;
+        }
+        let type_immune = match ailment {
+            ailments::BenchAilment::Frozen => self.types.contains(Type::Ice),
+            ailments::BenchAilment::Burned => self.types.contains(Type::Fire),
+            ailments::BenchAilment::Poisoned { .. }
+                => self.types.contains(Type::Poison)
+                    || self.types.contains(Type::Steel),
+            _ => false,
+        };
+        !type_immune && !self.hooks.status_immunities.fold(
+            false, |immune, hook| immune || hook.0(self, ailment))
+    }
+
+    pub fn inflict(&mut self, ailment: ailments::BenchAilment) -> bool {
+        if self.can_inflict(&ailment) {
+            self.perm.borrow_mut().status = ailment;
+            self.log(Event::StatusInflicted {
+                target: self.position,
+                status: ailment,
+            });
+            items::check_berry(self);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn confuse(&mut self, turns: u8) -> bool {
+        if self.status.flags.contains(ailments::BattlerAilmentFlags::CONFUSED)
+            || self.overlay.ability == vdex::Ability::OwnTempo
+            || self.side_has(SideCondition::Safeguard)
+        {
+            false
+        } else {
+            self.status.flags.insert(ailments::BattlerAilmentFlags::CONFUSED);
+            self.status.remaining_confused_attacks = turns;
+            self.log(Event::Confused { target: self.position });
+            items::check_berry(self);
+            true
+        }
+    }
+
+    pub fn flinch(&mut self) -> bool {
+        let substituted = self.status.flags
+            .contains(ailments::BattlerAilmentFlags::SUBSTITUTED);
+        if self.moved || substituted
+            || self.overlay.ability == vdex::Ability::InnerFocus
+        {
+            false
+        } else {
+            self.flinched = true;
+            true
+        }
+    }
+
+    pub fn change_stats(
+        &mut self, changes: [i8; moves::CHANGEABLE_STATS]
+    ) -> [i8; moves::CHANGEABLE_STATS] {
+        let mut applied = [0; moves::CHANGEABLE_STATS];
         for i in 0..moves::CHANGEABLE_STATS {
             let change = changes[i].max(-12).min(12);
             let stat = &mut self.stat_changes[i];
-            *stat = (*stat + change).max(-6).min(6);
+            let changed = (*stat + change).max(-6).min(6);
+            applied[i] = changed - *stat;
+            *stat = changed;
+            if change != 0 {
+                let target = self.position;
+                let stat = Stat::from_repr(i as _).unwrap();
+                self.log(if applied[i] != 0 {
+                    Event::StatChange { target, stat, change: applied[i] }
+                } else {
+                    Event::StatChangeFailed { target, stat, change }
+                });
+            }
         }
+        applied
+    }
+
+    pub fn change_stats_by_foe(
+        &mut self, changes: [i8; moves::CHANGEABLE_STATS]
+    ) -> [i8; moves::CHANGEABLE_STATS] {
+        let mut allowed = changes;
+        for i in 0..moves::CHANGEABLE_STATS {
+            if allowed[i] < 0 {
+                let stat = Stat::from_repr(i as _).unwrap();
+                if self.hooks.stat_drop_blocks.fold(
+                    false, |blocked, hook| blocked || hook.0(self, stat))
+                {
+                    allowed[i] = 0;
+                }
+            }
+        }
+        self.change_stats(allowed)
     }
 
     pub fn efficacy(&self, typ: Type) -> f64 {
@@ -151,6 +567,40 @@ impl BattlePokemon {
         }
     }
 
+    pub fn speed(&self) -> u16 {
+        let mut speed = self.stat(Stat::Speed, false);
+        if self.side_has(SideCondition::Tailwind) {
+            speed = speed.saturating_mul(2);
+        }
+        if let Some(HeldItem::ChoiceScarf) = self.held_item() {
+            speed = speed.saturating_mul(3) / 2;
+        }
+        if self.is_paralyzed() {
+            speed / 4
+        } else {
+            speed
+        }
+    }
+
+    pub fn must_struggle(&self) -> bool {
+        let perm = self.perm.borrow();
+        (0..4).all(|slot| {
+            self.overlay.moves[slot].is_none() || perm.pp[slot] == 0
+        })
+    }
+
+    pub fn chosen_move(&self, slot: u8) -> Option<&'static moves::Move> {
+        if self.must_struggle() {
+            Some(&vdex::pokedex().moves[STRUGGLE])
+        } else {
+            self.overlay.moves[slot as usize]
+        }
+    }
+
+    pub fn is_confused(&self) -> bool {
+        self.status.flags.contains(ailments::BattlerAilmentFlags::CONFUSED)
+    }
+
     pub fn is_paralyzed(&self) -> bool {
         if let ailments::BenchAilment::Paralyzed = self.perm.borrow().status {
             true
@@ -198,7 +648,7 @@ pub struct DamageContext {
     pub target: Current,
     pub slot: u8,
     pub mov: &'static moves::Move,
-    pub typ: Type,
+    pub typ: Option<Type>,
     pub power: u8,
     pub target_count: u8,
     pub class: moves::DamageClass,
@@ -223,6 +673,45 @@ impl DamageContext {
         percent_chance > 0 && rng.gen_range(0, 100) < percent_chance
     }
 
+    pub fn gen_meta_event<R: rand::Rng>(
+        &self, percent_chance: u8, rng: &mut R
+    ) -> bool {
+        // Status moves list their guaranteed effects with a chance of zero.
+        if percent_chance == 0 && self.mov.power == 0 {
+            true
+        } else {
+            DamageContext::gen_event(percent_chance, rng)
+        }
+    }
+
+    pub fn gen_ailment<R: rand::Rng>(
+        &self, rng: &mut R
+    ) -> Option<ailments::BenchAilment> {
+        match self.mov.meta.ailment {
+            moves::Ailment::Paralysis => {
+                if let moves::Effect::ParalyzeTarget = self.mov.effect {
+                    if self.effectiveness() == 0.0 {
+                        return None;
+                    }
+                }
+                Some(ailments::BenchAilment::Paralyzed)
+            },
+            moves::Ailment::Sleep => Some(ailments::BenchAilment::Asleep {
+                remaining_turns: rng.gen_range(1, 5),
+            }),
+            moves::Ailment::Freeze => Some(ailments::BenchAilment::Frozen),
+            moves::Ailment::Burn => Some(ailments::BenchAilment::Burned),
+            moves::Ailment::Poison => Some(ailments::BenchAilment::Poisoned {
+                bad: if let moves::Effect::Toxic = self.mov.effect {
+                    true
+                } else {
+                    false
+                },
+            }),
+            _ => None,
+        }
+    }
+
     pub fn new_basic<R: rand::Rng>(
         user: &Current, target: &Current, slot: u8, mov: &'static moves::Move,
         target_count: u8, rng: &mut R
@@ -232,7 +721,7 @@ impl DamageContext {
             target: target.clone(),
             slot,
             mov,
-            typ: mov.typ,
+            typ: Some(mov.typ),
             power: mov.power,
             target_count,
             class: mov.damage_class,
@@ -240,9 +729,40 @@ impl DamageContext {
         }
     }
 
-    pub fn accuracy(&self) -> f64 {
+    pub fn new_confusion(user: &Current, mov: &'static moves::Move) -> Self {
+        DamageContext {
+            user: user.clone(),
+            target: user.clone(),
+            slot: 4,
+            mov,
+            typ: None,
+            power: 40,
+            target_count: 1,
+            class: moves::DamageClass::Physical,
+            critical: false,
+        }
+    }
+
+    // Confusion damage targets its own user, and a second read of the same
+    // lock on one thread may deadlock, so the guard is shared in that case.
+    fn with_both<T>(
+        &self, f: impl FnOnce(&BattlePokemon, &BattlePokemon) -> T
+    ) -> T {
         let user = self.user.borrow();
-        let target = self.target.borrow();
+        if self.user.ptr_eq(&self.target) {
+            f(&user, &user)
+        } else {
+            f(&user, &self.target.borrow())
+        }
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        self.with_both(|user, target| self.accuracy_between(user, target))
+    }
+
+    fn accuracy_between(
+        &self, user: &BattlePokemon, target: &BattlePokemon
+    ) -> f64 {
         if let Some(base_percent) = self.mov.accuracy {
             let base = base_percent as f64 / 100.0;
             let stat_change = (user.stat_changes[Stat::Accuracy.repr() as usize]
@@ -264,50 +784,85 @@ impl DamageContext {
                 _ => unreachable!(),
             };
             let user_modi = user.hooks.user_accuracy_modifiers.fold(
-                stat_modi, |modi, func| modi * func.0(self));
+                stat_modi, |modi, func| modi * func.0(self, user, target));
             let target_modi = target.hooks.target_accuracy_modifiers.fold(
-                user_modi, |modi, func| modi * func.0(self));
+                user_modi, |modi, func| modi * func.0(self, user, target));
             target_modi
         } else {
             1.0
         }
     }
 
+    pub fn effective_critical(&self) -> bool {
+        self.critical_against(&self.target.borrow())
+    }
+
+    pub fn critical_against(&self, target: &BattlePokemon) -> bool {
+        !target.side_has(SideCondition::LuckyChant)
+            && target.hooks.critical_cancels.fold(
+                self.critical, |crit, cancel| crit && !cancel)
+    }
+
+    pub fn effectiveness(&self) -> f64 {
+        self.effectiveness_against(&self.target.borrow())
+    }
+
+    pub fn effectiveness_against(&self, target: &BattlePokemon) -> f64 {
+        match self.typ {
+            Some(typ) => target.efficacy(typ),
+            None => 1.0,
+        }
+    }
+
     pub fn calc_max_damage(&self) -> u16 {
-        let user = self.user.borrow();
-        let target = self.target.borrow();
-        let critical = target.hooks.critical_cancels.fold(
-            self.critical, |crit, cancel| crit && !cancel);
+        self.with_both(|user, target| self.max_damage_between(user, target))
+    }
+
+    fn max_damage_between(
+        &self, user: &BattlePokemon, target: &BattlePokemon
+    ) -> u16 {
+        let critical = self.critical_against(target);
 
         let level_factor = ((2 * user.overlay.level) / 5) + 2;
-        let power = user.hooks.power_modifiers.fold(
-            self.power as f64, |pow, func| pow * func.0(self)).trunc();
+        // Hurting itself in confusion isn't a move, so nothing boosts it.
+        let power = if self.typ.is_none() {
+            self.power as f64
+        } else {
+            user.hooks.power_modifiers.fold(
+                self.power as f64, |pow, func| pow * func.0(self, user, target))
+                .trunc()
+        };
 
         let attack_stat = user.stat(match self.class {
             moves::DamageClass::Special => Stat::SpecialAttack,
             _ => Stat::Attack,
         }, critical);
         let attack = user.hooks.attack_modifiers.fold(
-            attack_stat as f64, |atk, func| atk * func.0(self)).trunc();
+            attack_stat as f64, |atk, func| atk * func.0(self, user, target))
+            .trunc();
 
         let defense_stat = target.stat(match self.class {
             moves::DamageClass::Special => Stat::SpecialDefense,
             _ => Stat::Defense,
         }, critical);
         let defense = target.hooks.defense_modifiers.fold(
-            defense_stat as f64, |def, func| def * func.0(self)).trunc();
+            defense_stat as f64, |def, func| def * func.0(self, user, target))
+            .trunc();
 
-        let efficacy = target.efficacy(self.typ);
+        let (efficacy, stab) = match self.typ {
+            Some(typ) => (target.efficacy(typ), user.stab(typ)),
+            None => (1.0, 1.0),
+        };
         if efficacy == 0.0 {
             0
         } else {
             let base_modi = if critical { 2.0 } else { 1.0 }
                 * if self.target_count > 1 { 0.75 } else { 1.0 }
-                * user.stab(self.typ) * efficacy;
+                * stab * efficacy;
             let user_modi = user.hooks.user_damage_modifiers.fold(
-                base_modi, |modi, func| modi * func.0(self));
+                base_modi, |modi, func| modi * func.0(self, user, target));
             let target_modi = target.hooks.target_damage_modifiers.fold(
-                user_modi, |modi, func| modi * func.0(self));
+                user_modi, |modi, func| modi * func.0(self, user, target));
 
             let max = (((((((level_factor as f64) * power) * attack) / defense)
                 .trunc() / 50.0).trunc() + 2.0) * target_modi).trunc();
@@ -315,16 +870,95 @@ impl DamageContext {
         }
     }
 
+    fn focus_sash(&self, dmg: u16) -> u16 {
+        let mut target = self.target.borrow_mut();
+        let hp = target.perm.borrow().hp;
+        match target.held_item() {
+            Some(HeldItem::FocusSash)
+                if hp == target.overlay.stat(Stat::HP) && dmg >= hp =>
+            {
+                target.consume_item();
+                hp - 1
+            },
+            _ => dmg,
+        }
+    }
+
+    fn fire_hit_triggers<R: rand::Rng>(&self, damage: u16, rng: &mut R) {
+        let mut triggers = vec![(Trigger::OnDamaged, &self.target, &self.user)];
+        if self.mov.flags.contains(moves::Flags::CONTACT) {
+            triggers.push((Trigger::OnContact, &self.target, &self.user));
+        }
+        triggers.push((Trigger::AfterMoveHit, &self.user, &self.target));
+        for (trigger, current, other) in triggers {
+            let mut ctx =
+                TriggerContext::new(trigger, current, Some(other), &mut *rng);
+            ctx.mov = Some(self.mov);
+            ctx.damage = damage;
+            ctx.fire();
+        }
+    }
+
     pub fn do_damage<R: rand::Rng>(&self, rng: &mut R) -> u16 {
         let max = self.calc_max_damage();
         let dmg = ((max * rng.gen_range(85, 101)) / 100).max(1).min(max);
         if dmg > 0 {
-            self.target.borrow_mut().direct_damage(dmg)
+            let critical = self.effective_critical();
+            let effectiveness = self.effectiveness();
+            let resisted = items::resist_berry(self, &self.target.borrow());
+            if resisted {
+                self.target.borrow_mut().consume_item();
+            }
+            let dmg = self.focus_sash(dmg);
+            let dealt = self.target.borrow_mut()
+                .take_damage(dmg, critical, effectiveness);
+            if dealt > 0 && self.typ.is_some() {
+                self.user.borrow_mut().dealt_damage = true;
+                self.fire_hit_triggers(dealt, rng);
+            }
+            dealt
         } else {
             0
         }
     }
 
+    pub fn roll_hit<R: rand::Rng>(&self, rng: &mut R) -> bool {
+        let acc = self.accuracy();
+        if acc >= 1.0 || rng.gen_range(0.0, 1.0) < acc {
+            true
+        } else {
+            let target = self.target.borrow().position;
+            let user = self.user.borrow();
+            user.log(Event::Miss { user: user.position, target });
+            false
+        }
+    }
+
+    pub fn stats_change_user(&self) -> bool {
+        match self.mov.target {
+            moves::Target::User
+                | moves::Target::UsersField
+                | moves::Target::UserOrAlly
+                => return true,
+            _ => (),
+        }
+        if self.mov.power == 0 {
+            return false;
+        }
+        // Damaging moves only change the target's stats if they say so; the
+        // rest (Metal Claw, Close Combat, Overheat, ...) change the user's.
+        match self.mov.effect {
+            moves::Effect::ChanceLowerTargetAttack
+                | moves::Effect::ChanceLowerTargetDefense
+                | moves::Effect::ChanceLowerTargetSpeed
+                | moves::Effect::ChanceLowerTargetSpecialAttack
+                | moves::Effect::ChanceLowerTargetSpecialDefense
+                | moves::Effect::ChanceLowerTargetAccuracy
+                => false,
+            _ => true,
+        }
+    }
+
     pub fn execute_basic_core<R: rand::Rng>(&self, rng: &mut R) -> u16 {
         let dmg = if self.mov.power > 0 {
             self.do_damage(rng)
@@ -336,14 +970,31 @@ impl DamageContext {
             self.user.borrow_mut().direct_percentage(dmg, meta.recoil);
             let max_hp = self.user.borrow().overlay.stat(Stat::HP);
             self.user.borrow_mut().direct_percentage(max_hp, meta.healing);
-            if DamageContext::gen_event(meta.ailment_chance, rng) {
-                // TODO: apply ailments
+            if self.gen_meta_event(meta.ailment_chance, rng) {
+                if let Some(ailment) = self.gen_ailment(rng) {
+                    let inflicted = self.target.borrow_mut().inflict(ailment);
+                    if inflicted {
+                        let mut ctx = TriggerContext::new(
+                            Trigger::OnStatusInflicted, &self.target,
+                            Some(&self.user), &mut *rng);
+                        ctx.status = Some(ailment);
+                        ctx.fire();
+                    }
+                } else if let moves::Ailment::Confusion = meta.ailment {
+                    let turns = rng.gen_range(1, 5);
+                    self.target.borrow_mut().confuse(turns);
+                }
             }
             if DamageContext::gen_event(meta.flinch_chance, rng) {
-                // TODO: flinching
+                self.target.borrow_mut().flinch();
             }
-            if DamageContext::gen_event(meta.stat_chance, rng) {
-                self.user.borrow_mut().change_stats(meta.stat_changes);
+            if self.gen_meta_event(meta.stat_chance, rng) {
+                if self.stats_change_user() {
+                    self.user.borrow_mut().change_stats(meta.stat_changes);
+                } else {
+                    self.target.borrow_mut()
+                        .change_stats_by_foe(meta.stat_changes);
+                }
             }
         }
         dmg
@@ -352,8 +1003,7 @@ impl DamageContext {
     pub fn execute_basic_move<R: rand::Rng>(&self, rng: &mut R) -> u16 {
         // Moves that hit once, and applying recoil or healing to the user, and
         // an ailment, flinching, or stat changes to the target.
-        let acc = self.accuracy();
-        if acc >= 1.0 || rng.gen_range(0.0, 1.0) < acc {
+        if self.roll_hit(rng) {
             self.execute_basic_core(rng)
         } else {
             0
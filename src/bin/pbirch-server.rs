@@ -0,0 +1,11 @@
+use pbirch::rules::Ruleset;
+use std::env;
+use std::net::TcpListener;
+
+fn main() -> std::io::Result<()> {
+    let address = env::args().nth(1)
+        .unwrap_or_else(|| "127.0.0.1:6310".to_string());
+    let listener = TcpListener::bind(&address)?;
+    eprintln!("Listening on {}", address);
+    pbirch::server::run(listener, Ruleset::standard())
+}
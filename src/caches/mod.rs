@@ -1,2 +1,3 @@
 pub mod movesets;
+pub mod names;
 pub mod pokemon;
@@ -0,0 +1,111 @@
+use crate::caches::pokemon::species_ref;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Once;
+use vdex::{Ability, Enum, Nature};
+use vdex::items::{self, ItemId};
+use vdex::moves::{self, MoveId};
+use vdex::pokemon::{self, PokemonId};
+
+pub struct Names {
+    pub pokemon: HashMap<String, PokemonId>,
+    pub moves: HashMap<String, MoveId>,
+    pub items: HashMap<String, ItemId>,
+    pub abilities: HashMap<String, Ability>,
+    pub natures: HashMap<String, Nature>,
+}
+
+static mut CACHE: Option<Names> = None;
+static CACHE_ONCE: Once = Once::new();
+
+// Alternate forms are separate Pokémon of the same species, listed in this
+// order, and are named "Species-Form" as on Showdown.
+const FORMS: &[(&str, &[&str])] = &[
+    ("Deoxys", &["Normal", "Attack", "Defense", "Speed"]),
+    ("Wormadam", &["Plant", "Sandy", "Trash"]),
+    ("Giratina", &["Altered", "Origin"]),
+    ("Shaymin", &["Land", "Sky"]),
+    ("Rotom", &["", "Heat", "Wash", "Frost", "Fan", "Mow"]),
+];
+
+pub fn normalize(name: &str) -> String {
+    // The gender symbols are the only difference between the two Nidoran.
+    name.chars().flat_map(|c| match c {
+        '♀' => 'f'.to_lowercase(),
+        '♂' => 'm'.to_lowercase(),
+        _ => c.to_lowercase(),
+    }).filter(|c| c.is_alphanumeric()).collect()
+}
+
+fn form_name(species: &str, index: usize) -> Option<&'static str> {
+    let (_, forms) = FORMS.iter().find(|(name, _)| *name == species)?;
+    forms.get(index).cloned().filter(|form| !form.is_empty())
+}
+
+pub fn pokemon_name(pokemon_id: PokemonId) -> String {
+    let species = species_ref(pokemon_id);
+    let index = species.pokemon.iter().position(|p| p.id == pokemon_id)
+        .unwrap_or(0);
+    match form_name(&species.name, index) {
+        Some(form) if index > 0 => format!("{}-{}", species.name, form),
+        _ => species.name.clone(),
+    }
+}
+
+pub fn enum_name<T: Debug>(value: T) -> String {
+    let mut name = String::new();
+    for c in format!("{:?}", value).chars() {
+        if c.is_uppercase() && !name.is_empty() {
+            name.push(' ');
+        }
+        name.push(c);
+    }
+    name
+}
+
+fn enum_map<T: Copy + Debug, F: Fn(u8) -> Option<T>>(
+    from_repr: F
+) -> HashMap<String, T> {
+    (0..=u8::max_value()).filter_map(from_repr)
+        .map(|value| (normalize(&format!("{:?}", value)), value))
+        .collect()
+}
+
+pub fn names() -> &'static Names {
+    unsafe {
+        CACHE_ONCE.call_once(|| {
+            let dex = vdex::pokedex();
+            let mut pokemon = HashMap::new();
+            for i in 0..pokemon::SPECIES_COUNT {
+                let species = &dex.species[pokemon::SpeciesId(i as u16)];
+                if let Some(first) = species.pokemon.first() {
+                    pokemon.insert(normalize(&species.name), first.id);
+                }
+                for (index, form) in species.pokemon.iter().enumerate() {
+                    if let Some(name) = form_name(&species.name, index) {
+                        let name = format!("{}-{}", species.name, name);
+                        pokemon.insert(normalize(&name), form.id);
+                    }
+                }
+            }
+            let mut move_names = HashMap::new();
+            for i in 0..moves::MOVE_COUNT {
+                let mov = &dex.moves[MoveId(i as u16)];
+                move_names.insert(normalize(&mov.name), mov.id);
+            }
+            let mut item_names = HashMap::new();
+            for i in 0..items::ITEM_COUNT {
+                let item = &dex.items[ItemId(i as u16)];
+                item_names.insert(normalize(&item.name), item.id);
+            }
+            CACHE = Some(Names {
+                pokemon,
+                moves: move_names,
+                items: item_names,
+                abilities: enum_map(|i| Ability::from_repr(i.into())),
+                natures: enum_map(|i| Nature::from_repr(i.into())),
+            });
+        });
+        CACHE.as_ref().unwrap()
+    }
+}
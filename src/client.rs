@@ -0,0 +1,122 @@
+use crate::events::Event;
+use crate::exec::turn::{Action, BattleOutcome};
+use crate::protocol::{ClientMessage, ServerMessage};
+use crate::team::Team;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use vdex::moves::MoveId;
+use vdex::pokemon::PokemonId;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MoveOption {
+    pub slot: u8,
+    pub mov: MoveId,
+    pub pp: u8,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TurnRequest {
+    pub moves: Vec<MoveOption>,
+    pub switches: Vec<usize>,
+}
+
+impl TurnRequest {
+    pub fn usable_moves(&self) -> impl Iterator<Item = &MoveOption> {
+        self.moves.iter().filter(|option| option.pp > 0)
+    }
+
+    pub fn actions(&self) -> Vec<Action> {
+        self.usable_moves().map(|option| Action::Move(option.slot))
+            .chain(self.switches.iter().map(|index| Action::Switch(*index)))
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Update {
+    Preview { opponent: Vec<PokemonId>, picks: usize },
+    TurnRequest(TurnRequest),
+    ReplaceRequest(Vec<usize>),
+    Event(Event),
+    Error(String),
+    End(BattleOutcome),
+}
+
+pub struct Client {
+    pub side: usize,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+impl Client {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let side = match Self::read_message(&mut reader)? {
+            ServerMessage::Side(side) => side,
+            _ => return Err(invalid_data("expected a side")),
+        };
+        Ok(Self { side, reader, writer: stream })
+    }
+
+    fn read_message(
+        reader: &mut BufReader<TcpStream>
+    ) -> io::Result<ServerMessage> {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof, "server disconnected"));
+        }
+        ServerMessage::decode(&line)
+            .ok_or_else(|| invalid_data("unrecognized message"))
+    }
+
+    fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        writeln!(self.writer, "{}", message.encode())
+    }
+
+    pub fn submit_team(&mut self, team: &Team) -> io::Result<()> {
+        self.send(&ClientMessage::Team(team.clone()))
+    }
+
+    pub fn send_action(&mut self, action: Action) -> io::Result<()> {
+        self.send(&ClientMessage::Action(action))
+    }
+
+    pub fn send_order(&mut self, order: Vec<usize>) -> io::Result<()> {
+        self.send(&ClientMessage::Order(order))
+    }
+
+    pub fn send_replacement(&mut self, index: usize) -> io::Result<()> {
+        self.send(&ClientMessage::Replace(index))
+    }
+
+    pub fn receive(&mut self) -> io::Result<Update> {
+        loop {
+            return Ok(match Self::read_message(&mut self.reader)? {
+                ServerMessage::Side(side) => {
+                    self.side = side;
+                    continue;
+                },
+                ServerMessage::Preview { species, picks }
+                    => Update::Preview { opponent: species, picks },
+                ServerMessage::TurnRequest { moves, switches } => {
+                    let moves = moves.iter().enumerate()
+                        .filter_map(|(slot, option)| option.map(|(mov, pp)|
+                            MoveOption { slot: slot as u8, mov, pp }))
+                        .collect();
+                    Update::TurnRequest(TurnRequest { moves, switches })
+                },
+                ServerMessage::ReplaceRequest { switches }
+                    => Update::ReplaceRequest(switches),
+                ServerMessage::Event(event) => Update::Event(event),
+                ServerMessage::Error(message) => Update::Error(message),
+                ServerMessage::End(outcome) => Update::End(outcome),
+            });
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::ailments::BenchAilment;
+use crate::exec::premove::MoveBlock;
+use crate::exec::residual::Residual;
+use crate::exec::turn::BattleOutcome;
+use crate::field::{Hazard, SideCondition, Weather};
+use crate::formats::AbsoluteTarget;
+use crate::shared::Shared;
+use vdex::items::ItemId;
+use vdex::moves::MoveId;
+use vdex::Stat;
+
+pub type Log = Shared<Vec<Event>>;
+
+pub fn new_log() -> Log {
+    Shared::new(Vec::new())
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Event {
+    Turn(u16),
+    MoveUsed { user: AbsoluteTarget, mov: MoveId },
+    MoveBlocked { user: AbsoluteTarget, reason: MoveBlock },
+    MoveFailed { user: AbsoluteTarget },
+    Miss { user: AbsoluteTarget, target: AbsoluteTarget },
+    Damage {
+        target: AbsoluteTarget,
+        amount: u16,
+        critical: bool,
+        effectiveness: f64,
+    },
+    Heal { target: AbsoluteTarget, amount: u16 },
+    StatChange { target: AbsoluteTarget, stat: Stat, change: i8 },
+    StatChangeFailed { target: AbsoluteTarget, stat: Stat, change: i8 },
+    StatusInflicted { target: AbsoluteTarget, status: BenchAilment },
+    StatusCured { target: AbsoluteTarget },
+    Confused { target: AbsoluteTarget },
+    ConfusionEnded { target: AbsoluteTarget },
+    Residual { target: AbsoluteTarget, kind: Residual },
+    PerishCount { target: AbsoluteTarget, count: u8 },
+    WeatherStarted { weather: Weather },
+    WeatherEnded { weather: Weather },
+    SideConditionStarted { side: usize, condition: SideCondition },
+    SideConditionEnded { side: usize, condition: SideCondition },
+    HazardSet { side: usize, hazard: Hazard, layers: u8 },
+    HazardRemoved { side: usize, hazard: Hazard },
+    Hazard { target: AbsoluteTarget, hazard: Hazard },
+    ItemConsumed { target: AbsoluteTarget, item: ItemId },
+    ItemRemoved { target: AbsoluteTarget, item: ItemId },
+    ItemGained { target: AbsoluteTarget, item: ItemId },
+    Embargoed { target: AbsoluteTarget },
+    EmbargoEnded { target: AbsoluteTarget },
+    Faint { target: AbsoluteTarget },
+    Switch { target: AbsoluteTarget, index: usize },
+    Forfeit { side: usize },
+    End(BattleOutcome),
+}
@@ -1 +1,4 @@
 pub mod moves;
+pub mod premove;
+pub mod residual;
+pub mod turn;
@@ -1,7 +1,12 @@
 use crate::battle::{Current, DamageContext};
+use crate::events::Event;
+use crate::field::{self, Hazard, SideCondition, Weather};
 use crate::formats::{AbsoluteTarget, RelativeTarget};
 use crate::hooks;
-use vdex::moves::{self, Effect, Move};
+use crate::items::{self, HeldItem};
+use vdex::moves::{self, Effect, Move, MoveId};
+
+pub const STRUGGLE: MoveId = MoveId(164);
 
 pub fn get_targets(user: &Current, mov: &'static Move) -> Vec<RelativeTarget> {
     match mov.target {
@@ -28,10 +33,112 @@ pub fn get_targets(user: &Current, mov: &'static Move) -> Vec<RelativeTarget> {
     }
 }
 
+fn steal_item(user: &Current, target: &Current) -> bool {
+    if user.borrow().perm.borrow().held.is_some()
+        || !target.borrow().can_lose_item()
+    {
+        return false;
+    }
+    let item = target.borrow_mut().remove_item();
+    user.borrow_mut().gain_item(item);
+    item.is_some()
+}
+
+fn swap_items(user: &Current, target: &Current) -> bool {
+    let user_item = user.borrow().perm.borrow().held;
+    let target_item = target.borrow().perm.borrow().held;
+    if (user_item.is_none() && target_item.is_none())
+        || !target.borrow().can_lose_item()
+    {
+        return false;
+    }
+    user.borrow_mut().gain_item(target_item);
+    target.borrow_mut().gain_item(user_item);
+    true
+}
+
+pub fn is_implemented(mov: &Move) -> bool {
+    // `execute_move_core` refuses anything this rejects, so this is the one
+    // place that decides which moves the engine can run. Moves that pick a
+    // specific move of the target have no targeting yet.
+    if let moves::Target::SpecificMove = mov.target {
+        return false;
+    }
+    if Weather::from_move(mov.id).is_some()
+        || Hazard::from_move(mov.id).is_some()
+        || SideCondition::from_move(mov.id).is_some()
+        || mov.id == STRUGGLE
+        || mov.id == field::RAPID_SPIN
+        || field::WEATHER_HEALING_MOVES.contains(&mov.id)
+        || mov.id == items::KNOCK_OFF
+        || mov.id == items::RECYCLE
+        || mov.id == items::EMBARGO
+        || items::ITEM_STEALING_MOVES.contains(&mov.id)
+        || items::ITEM_SWAPPING_MOVES.contains(&mov.id)
+        || items::BERRY_STEALING_MOVES.contains(&mov.id)
+    {
+        return true;
+    }
+    match mov.effect {
+        Effect::RegularDamage | Effect::SleepTarget
+            | Effect::ChancePoisonTarget | Effect::HealUserHalfInflicted
+            | Effect::ChanceBurnTarget | Effect::ChanceFreezeTarget
+            | Effect::ChanceParalyzeTarget | Effect::NeverMisses
+            | Effect::LowerTargetAttack | Effect::LowerTargetDefense
+            | Effect::LowerTargetSpeed | Effect::LowerTargetAccuracy
+            | Effect::LowerTargetEvasion | Effect::ChanceFlinchTarget
+            | Effect::HealUserByHalfMaxHP | Effect::PayDay
+            | Effect::IncreasedCritical | Effect::QuarterRecoil
+            | Effect::ConfuseTarget | Effect::LowerTargetAttack2
+            | Effect::LowerTargetDefense2 | Effect::LowerTargetSpeed2
+            | Effect::LowerTargetSpecialDefense2 | Effect::PoisonTarget
+            | Effect::ParalyzeTarget | Effect::ChanceLowerTargetAttack
+            | Effect::ChanceLowerTargetDefense | Effect::ChanceLowerTargetSpeed
+            | Effect::ChanceLowerTargetSpecialAttack
+            | Effect::ChanceLowerTargetSpecialDefense
+            | Effect::ChanceLowerTargetAccuracy | Effect::ChanceConfuseTarget
+            | Effect::VitalThrow | Effect::Fast | Effect::Toxic
+            | Effect::RaiseUserAttack | Effect::RaiseUserDefense
+            | Effect::RaiseUserSpecialAttack | Effect::RaiseUserEvasion
+            | Effect::RaiseUserAttack2 | Effect::RaiseUserDefense2
+            | Effect::RaiseUserSpeed2 | Effect::RaiseUserSpecialAttack2
+            | Effect::RaiseUserSpecialDefense2 | Effect::FaintUser
+            | Effect::DreamEater | Effect::Haze | Effect::Hit2To5Times
+            | Effect::LightScreen | Effect::SuperFang | Effect::DragonRage
+            | Effect::HitTwice | Effect::HalfRecoilIfMiss | Effect::Mist
+            | Effect::Reflect | Effect::Splash | Effect::UserLevelDamage
+            | Effect::Psywave | Effect::PainSplit | Effect::Snore
+            | Effect::DestinyBond | Effect::MoreDamageWhenLessUserHP
+            => true,
+        _ => false,
+    }
+}
+
 pub fn execute_move<F, R>(
+    user: &Current, slot: u8, mov: &'static Move,
+    resolve_targets: F, rng: &mut R
+) -> bool where F: FnMut(&Vec<AbsoluteTarget>) -> Vec<Current>, R: rand::Rng {
+    let position = user.borrow().position;
+    user.borrow().log(Event::MoveUsed { user: position, mov: mov.id });
+    user.borrow_mut().dealt_damage = false;
+    let executed = execute_move_core(user, slot, mov, resolve_targets, rng);
+    // Life Orb takes its toll once per move, however many times it hit.
+    if user.borrow().dealt_damage && !user.borrow().is_fainted() {
+        items::life_orb(&mut user.borrow_mut());
+    }
+    if !executed {
+        user.borrow().log(Event::MoveFailed { user: position });
+    }
+    executed
+}
+
+fn execute_move_core<F, R>(
     user: &Current, slot: u8, mov: &'static Move,
     mut resolve_targets: F, rng: &mut R
 ) -> bool where F: FnMut(&Vec<AbsoluteTarget>) -> Vec<Current>, R: rand::Rng {
+    if !is_implemented(mov) {
+        return false;
+    }
     let mut abs_targets = Vec::new();
     for rel_target in get_targets(user, mov) {
         abs_targets.push(rel_target.absolute(&user.borrow().position));
@@ -52,6 +159,92 @@ pub fn execute_move<F, R>(
     let create_context = |target: &Current, rng: &mut R| -> DamageContext {
         DamageContext::new_basic(user, target, slot, mov, target_count, rng)
     };
+    if let Some(weather) = Weather::from_move(mov.id) {
+        return user.borrow().set_weather(weather, Some(field::WEATHER_TURNS));
+    }
+    if let Some(hazard) = Hazard::from_move(mov.id) {
+        let foe_side = 1 - user.borrow().side();
+        return user.borrow().set_hazard(foe_side, hazard);
+    }
+    if mov.id == STRUGGLE {
+        // Typeless, never misses, and costs a quarter of the user's max HP.
+        for target in targets {
+            let mut ctx = create_context(&target, rng);
+            ctx.typ = None;
+            ctx.do_damage(rng);
+        }
+        let mut user = user.borrow_mut();
+        let max_hp = user.overlay.stat(vdex::Stat::HP);
+        user.direct_damage((max_hp / 4).max(1));
+        return true;
+    }
+    if mov.id == field::RAPID_SPIN {
+        for target in targets {
+            if create_context(&target, rng).execute_basic_move(rng) > 0 {
+                user.borrow().clear_hazards();
+            }
+        }
+        return true;
+    }
+    if mov.id == items::KNOCK_OFF {
+        for target in targets {
+            if create_context(&target, rng).execute_basic_move(rng) > 0
+                && target.borrow().can_lose_item()
+            {
+                target.borrow_mut().remove_item();
+            }
+        }
+        return true;
+    }
+    if items::ITEM_STEALING_MOVES.contains(&mov.id) {
+        for target in targets {
+            if create_context(&target, rng).execute_basic_move(rng) > 0 {
+                steal_item(user, &target);
+            }
+        }
+        return true;
+    }
+    if items::ITEM_SWAPPING_MOVES.contains(&mov.id) {
+        if target_count != 1 || !create_context(&targets[0], rng).roll_hit(rng) {
+            return false;
+        }
+        return swap_items(user, &targets[0]);
+    }
+    if mov.id == items::EMBARGO {
+        let mut embargoed = false;
+        for target in targets {
+            if create_context(&target, rng).roll_hit(rng) {
+                embargoed |= target.borrow_mut().embargo(items::EMBARGO_TURNS);
+            }
+        }
+        return embargoed;
+    }
+    if items::BERRY_STEALING_MOVES.contains(&mov.id) {
+        for target in targets {
+            if create_context(&target, rng).execute_basic_move(rng) == 0 {
+                continue;
+            }
+            let berry = match target.borrow().held_item() {
+                Some(HeldItem::Berry(berry)) => berry,
+                _ => continue,
+            };
+            target.borrow_mut().remove_item();
+            berry.eat(&mut user.borrow_mut());
+        }
+        return true;
+    }
+    if mov.id == items::RECYCLE {
+        return user.borrow_mut().recycle();
+    }
+    if let Some(condition) = SideCondition::from_move(mov.id) {
+        let turns = condition.turns();
+        return user.borrow().start_side_condition(condition, turns);
+    }
+    if field::WEATHER_HEALING_MOVES.contains(&mov.id) {
+        let weather = user.borrow().field.borrow().weather;
+        let max_hp = user.borrow().overlay.stat(vdex::Stat::HP);
+        return user.borrow_mut().direct_heal(weather.healing(max_hp)) > 0;
+    }
     match mov.effect {
         Effect::RegularDamage
             | Effect::SleepTarget
@@ -87,21 +280,31 @@ pub fn execute_move<F, R>(
             | Effect::ChanceConfuseTarget
             | Effect::VitalThrow
             | Effect::Fast
+            | Effect::Toxic
+            | Effect::RaiseUserAttack
+            | Effect::RaiseUserDefense
+            | Effect::RaiseUserSpecialAttack
+            | Effect::RaiseUserEvasion
+            | Effect::RaiseUserAttack2
+            | Effect::RaiseUserDefense2
+            | Effect::RaiseUserSpeed2
+            | Effect::RaiseUserSpecialAttack2
+            | Effect::RaiseUserSpecialDefense2
         => {
             for target in targets {
                 create_context(&target, rng).execute_basic_move(rng);
             }
         },
         Effect::FaintUser => {
-            // TODO: faint user
             for target in targets {
                 let key = hooks::HookKey::new_move(0, mov.id, 0);
                 target.borrow_mut().hooks.defense_modifiers.overlay
-                    .insert(key, hooks::DamageHook(|_| 0.5));
+                    .insert(key, hooks::DamageHook(|_, _, _| 0.5));
                 create_context(&target, rng).execute_basic_move(rng);
                 target.borrow_mut().hooks.defense_modifiers.overlay
                     .remove(&key);
             }
+            user.borrow_mut().faint();
         },
         Effect::DreamEater => {
             if target_count != 1 || !targets[0].borrow().is_asleep() {
@@ -109,39 +312,14 @@ pub fn execute_move<F, R>(
             }
             create_context(&targets[0], rng).execute_basic_move(rng);
         },
-        Effect::MirrorMove => {
-            // TODO: implement
-        },
-        Effect::RaiseUserAttack
-            | Effect::RaiseUserDefense
-            | Effect::RaiseUserSpecialAttack
-            | Effect::RaiseUserEvasion
-            | Effect::RaiseUserAttack2
-            | Effect::RaiseUserDefense2
-            | Effect::RaiseUserSpeed2
-            | Effect::RaiseUserSpecialAttack2
-            | Effect::RaiseUserSpecialDefense2
-        => {
-            // TODO: implement
-        },
         Effect::Haze => {
             for target in targets {
                 target.borrow_mut().stat_changes = [0; moves::CHANGEABLE_STATS];
             }
         },
-        Effect::Bide => {
-            // TODO: implement
-        },
-        Effect::Hit2To3TurnsThenConfuseUser => {
-            // TODO: implement
-        },
-        Effect::SwitchOutTarget => {
-            // TODO: implement
-        },
         Effect::Hit2To5Times => {
             for target in targets {
-                let acc = create_context(&target, rng).accuracy();
-                if acc >= 1.0 || rng.gen_range(0.0, 1.0) < acc {
+                if create_context(&target, rng).roll_hit(rng) {
                     let hits = match rng.gen_range(0, 6) {
                         0 | 1 => 2,
                         2 | 3 => 3,
@@ -155,26 +333,9 @@ pub fn execute_move<F, R>(
                 }
             }
         },
-        Effect::Conversion => {
-            // TODO: implement
-        },
-        Effect::Toxic => {
-            // TODO: implement
-        },
         Effect::LightScreen => {
-            // TODO: implement
-        },
-        Effect::TriAttack => {
-            // TODO: implement
-        },
-        Effect::Rest => {
-            // TODO: implement
-        },
-        Effect::OneHitKO => {
-            // TODO: implement
-        },
-        Effect::RazorWind => {
-            // TODO: implement
+            return user.borrow().start_side_condition(
+                SideCondition::LightScreen, field::SCREEN_TURNS);
         },
         Effect::SuperFang => {
             for target in targets {
@@ -191,13 +352,9 @@ pub fn execute_move<F, R>(
                 }
             }
         },
-        Effect::SixteenthHP2To5Turns => {
-            // TODO: implement
-        },
         Effect::HitTwice => {
             for target in targets {
-                let acc = create_context(&target, rng).accuracy();
-                if acc >= 1.0 || rng.gen_range(0.0, 1.0) < acc {
+                if create_context(&target, rng).roll_hit(rng) {
                     for _ in 0..2 {
                         create_context(&target, rng).execute_basic_core(rng);
                     }
@@ -207,8 +364,7 @@ pub fn execute_move<F, R>(
         Effect::HalfRecoilIfMiss => {
             for target in targets {
                 let ctx = create_context(&target, rng);
-                let acc = ctx.accuracy();
-                if acc >= 1.0 || rng.gen_range(0.0, 1.0) < acc {
+                if ctx.roll_hit(rng) {
                     ctx.execute_basic_core(rng);
                 } else {
                     let max = ctx.calc_max_damage();
@@ -218,45 +374,14 @@ pub fn execute_move<F, R>(
             }
         },
         Effect::Mist => {
-            // TODO: implement
-        },
-        Effect::FocusEnergy => {
-            // TODO: implement
-        },
-        Effect::Transform => {
-            // TODO: implement
+            return user.borrow().start_side_condition(
+                SideCondition::Mist, field::SCREEN_TURNS);
         },
         Effect::Reflect => {
-            // TODO: implement
-        },
-        Effect::SkyAttack => {
-            // TODO: implement
-        },
-        Effect::Twineedle => {
-            // TODO: implement
-        },
-        Effect::Substitute => {
-            // TODO: implement
-        },
-        Effect::RechargeNextTurn => {
-            // TODO: implement
-        },
-        Effect::Rage => {
-            // TODO: implement
-        },
-        Effect::Mimic => {
-            // TODO: implement
-        },
-        Effect::Metronome => {
-            // TODO: implement
-        },
-        Effect::LeechSeed => {
-            // TODO: implement
+            return user.borrow().start_side_condition(
+                SideCondition::Reflect, field::SCREEN_TURNS);
         },
         Effect::Splash => (),
-        Effect::Disable => {
-            // TODO: implement
-        },
         Effect::UserLevelDamage => {
             for target in targets {
                 if target.borrow().efficacy(mov.typ) > 0.0 {
@@ -274,12 +399,6 @@ pub fn execute_move<F, R>(
                 }
             }
         },
-        Effect::Counter => {
-            // TODO: implement
-        },
-        Effect::Encore => {
-            // TODO: implement
-        },
         Effect::PainSplit => {
             for target in targets {
                 let user_hp = user.borrow().perm.borrow().hp;
@@ -297,20 +416,8 @@ pub fn execute_move<F, R>(
                 create_context(&target, rng).execute_basic_move(rng);
             }
         },
-        Effect::Conversion2 => {
-            // TODO: implement
-        },
-        Effect::GuaranteeNextMoveHit => {
-            // TODO: implement
-        },
-        Effect::Sketch => {
-            // TODO: implement
-        },
-        Effect::SleepTalk => {
-            // TODO: implement
-        },
         Effect::DestinyBond => {
-            // TODO: implement
+            user.borrow_mut().destiny_bond = true;
         },
         Effect::MoreDamageWhenLessUserHP => {
             for target in targets {
@@ -334,19 +441,7 @@ pub fn execute_move<F, R>(
                 ctx.execute_basic_move(rng);
             }
         },
-        Effect::Spite => {
-            // TODO: implement
-        },
-        Effect::FalseSwipe => {
-            // TODO: implement
-        },
-        Effect::CurePartyStatus => {
-            // TODO: implement
-        },
-        Effect::TripleKick => {
-            // TODO: implement
-        },
-        _ => panic!("TODO: Not implemented yet!"),
+        _ => return false,
     }
     return true;
 }
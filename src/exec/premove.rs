@@ -0,0 +1,110 @@
+use crate::ailments::{BattlerAilmentFlags, BenchAilment};
+use crate::battle::{Current, DamageContext};
+use crate::events::Event;
+use vdex::moves::{self, Effect, Move, MoveId};
+use vdex::{Ability, Enum, Stat};
+
+// Flame Wheel, Sacred Fire, and Flare Blitz thaw the user out.
+pub const THAWING_MOVES: [MoveId; 3] = [MoveId(171), MoveId(220), MoveId(393)];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MoveBlock {
+    Asleep,
+    Frozen,
+    Flinched,
+    Confused,
+    Infatuated,
+    FullyParalyzed,
+}
+
+fn check_sleep(user: &Current, mov: &'static Move) -> Result<(), MoveBlock> {
+    let mut user = user.borrow_mut();
+    let remaining = match user.perm.borrow().status {
+        BenchAilment::Asleep { remaining_turns } => remaining_turns,
+        _ => return Ok(()),
+    };
+    if remaining == 0 {
+        user.perm.borrow_mut().status = BenchAilment::None;
+        user.status.flags.remove(BattlerAilmentFlags::NIGHTMARE);
+        user.log(Event::StatusCured { target: user.position });
+        Ok(())
+    } else {
+        // Each attempt to move uses up a turn of sleep.
+        user.perm.borrow_mut().status = BenchAilment::Asleep {
+            remaining_turns: remaining - 1,
+        };
+        match mov.effect {
+            Effect::Snore | Effect::SleepTalk => Ok(()),
+            _ => Err(MoveBlock::Asleep),
+        }
+    }
+}
+
+fn check_freeze<R: rand::Rng>(
+    user: &Current, mov: &'static Move, rng: &mut R
+) -> Result<(), MoveBlock> {
+    let user = user.borrow();
+    if !user.is_frozen() {
+        return Ok(());
+    }
+    if THAWING_MOVES.contains(&mov.id) || rng.gen_range(0, 5) == 0 {
+        user.perm.borrow_mut().status = BenchAilment::None;
+        user.log(Event::StatusCured { target: user.position });
+        Ok(())
+    } else {
+        Err(MoveBlock::Frozen)
+    }
+}
+
+fn check_flinch(user: &Current) -> Result<(), MoveBlock> {
+    let mut user = user.borrow_mut();
+    if !user.flinched {
+        return Ok(());
+    }
+    if user.overlay.ability == Ability::Steadfast {
+        let mut changes = [0; moves::CHANGEABLE_STATS];
+        changes[Stat::Speed.repr() as usize] = 1;
+        user.change_stats(changes);
+    }
+    Err(MoveBlock::Flinched)
+}
+
+fn check_confusion<R: rand::Rng>(
+    user: &Current, mov: &'static Move, rng: &mut R
+) -> Result<(), MoveBlock> {
+    {
+        let mut user = user.borrow_mut();
+        if !user.status.flags.contains(BattlerAilmentFlags::CONFUSED) {
+            return Ok(());
+        }
+        if user.status.remaining_confused_attacks == 0 {
+            user.status.flags.remove(BattlerAilmentFlags::CONFUSED);
+            user.log(Event::ConfusionEnded { target: user.position });
+            return Ok(());
+        }
+        user.status.remaining_confused_attacks -= 1;
+    }
+    if rng.gen() {
+        DamageContext::new_confusion(user, mov).do_damage(rng);
+        Err(MoveBlock::Confused)
+    } else {
+        Ok(())
+    }
+}
+
+pub fn check_move<R: rand::Rng>(
+    user: &Current, mov: &'static Move, rng: &mut R
+) -> Result<(), MoveBlock> {
+    check_sleep(user, mov)?;
+    check_freeze(user, mov, rng)?;
+    check_flinch(user)?;
+    check_confusion(user, mov, rng)?;
+    let flags = user.borrow().status.flags;
+    if flags.contains(BattlerAilmentFlags::INFATUATED) && rng.gen() {
+        return Err(MoveBlock::Infatuated);
+    }
+    if user.borrow().is_paralyzed() && rng.gen_range(0, 4) == 0 {
+        return Err(MoveBlock::FullyParalyzed);
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use crate::ailments::{BattlerAilmentFlags, BenchAilment};
+use crate::battle::{BattlePokemon, Current};
+use crate::events::Event;
+use crate::items::HeldItem;
+use vdex::Ability;
+use vdex::Stat;
+use vdex::Type;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Residual {
+    Weather,
+    Ingrain,
+    HeldItem,
+    LeechSeed,
+    Poison,
+    Burn,
+    Nightmare,
+    Curse,
+    PerishSong,
+}
+
+// Sleep counts down as the sleeper tries to move, not at the end of the turn.
+pub const ORDER: [Residual; 9] = [
+    Residual::Weather,
+    Residual::Ingrain,
+    Residual::HeldItem,
+    Residual::LeechSeed,
+    Residual::Poison,
+    Residual::Burn,
+    Residual::Nightmare,
+    Residual::Curse,
+    Residual::PerishSong,
+];
+
+fn fraction_damage(current: &Current, numerator: u16, denominator: u16) -> u16 {
+    let mut current = current.borrow_mut();
+    if current.overlay.ability == Ability::MagicGuard {
+        return 0;
+    }
+    let max_hp = current.overlay.stat(Stat::HP);
+    current.direct_damage(((max_hp * numerator) / denominator).max(1))
+}
+
+fn applies(kind: Residual, current: &BattlePokemon) -> bool {
+    let flags = current.status.flags;
+    match kind {
+        Residual::Weather => current.field.borrow().weather.damages(current),
+        Residual::Ingrain => flags.contains(BattlerAilmentFlags::ROOTED),
+        Residual::HeldItem => match current.held_item() {
+            Some(HeldItem::BlackSludge)
+                if !current.types.contains(Type::Poison) => true,
+            Some(HeldItem::Leftovers) | Some(HeldItem::BlackSludge)
+                => current.perm.borrow().hp < current.overlay.stat(Stat::HP),
+            _ => false,
+        },
+        Residual::LeechSeed => flags.contains(BattlerAilmentFlags::SEEDED),
+        Residual::Poison => current.is_poisoned(),
+        Residual::Burn => current.is_burned(),
+        Residual::Nightmare => flags.contains(BattlerAilmentFlags::NIGHTMARE)
+            && current.is_asleep(),
+        Residual::Curse => flags.contains(BattlerAilmentFlags::CURSED),
+        Residual::PerishSong => flags.contains(BattlerAilmentFlags::PERISHING),
+    }
+}
+
+pub fn apply(kind: Residual, current: &Current, foe: &Current) {
+    {
+        let current = current.borrow();
+        if current.is_fainted() || !applies(kind, &current) {
+            return;
+        }
+        current.log(Event::Residual { target: current.position, kind });
+    }
+    match kind {
+        Residual::Weather => {
+            fraction_damage(current, 1, 16);
+        },
+        Residual::Ingrain => {
+            let max_hp = current.borrow().overlay.stat(Stat::HP);
+            current.borrow_mut().direct_heal((max_hp / 16).max(1));
+        },
+        Residual::HeldItem => {
+            let (held, poison) = {
+                let current = current.borrow();
+                (current.held_item(), current.types.contains(Type::Poison))
+            };
+            if let (Some(HeldItem::BlackSludge), false) = (held, poison) {
+                fraction_damage(current, 1, 8);
+                return;
+            }
+            let max_hp = current.borrow().overlay.stat(Stat::HP);
+            current.borrow_mut().direct_heal((max_hp / 16).max(1));
+        },
+        Residual::LeechSeed => {
+            let drained = fraction_damage(current, 1, 8);
+            if !foe.borrow().is_fainted() {
+                foe.borrow_mut().direct_heal(drained);
+            }
+        },
+        Residual::Poison => {
+            let bad = match current.borrow().perm.borrow().status {
+                BenchAilment::Poisoned { bad } => bad,
+                _ => false,
+            };
+            if bad {
+                let turns = {
+                    let mut current = current.borrow_mut();
+                    let turns = &mut current.status.turns_badly_poisoned;
+                    *turns = (*turns + 1).min(15);
+                    *turns
+                };
+                fraction_damage(current, turns as u16, 16);
+            } else {
+                fraction_damage(current, 1, 8);
+            }
+        },
+        Residual::Burn => {
+            fraction_damage(current, 1, 8);
+        },
+        Residual::Nightmare | Residual::Curse => {
+            fraction_damage(current, 1, 4);
+        },
+        Residual::PerishSong => {
+            let mut current = current.borrow_mut();
+            let count = current.status.perish_count.saturating_sub(1);
+            current.status.perish_count = count;
+            current.log(Event::PerishCount { target: current.position, count });
+            if count == 0 {
+                current.status.flags.remove(BattlerAilmentFlags::PERISHING);
+                current.faint();
+            }
+        },
+    }
+}
@@ -0,0 +1,73 @@
+use crate::battle::Current;
+use std::cmp::Ordering;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Action {
+    Move(u8),
+    Switch(usize),
+    Forfeit,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Choice {
+    Turn(Action, Action),
+    Replace(usize, usize),
+    Forfeit(usize),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ActionError {
+    InvalidSlot,
+    EmptySlot,
+    NoPP,
+    UnimplementedMove,
+    ChoiceLocked,
+    InvalidSwitch,
+    AlreadyActive,
+    Fainted,
+    ReplacementPending,
+    NoReplacementNeeded,
+    BattleOver,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum BattleOutcome {
+    Battler1Wins,
+    Battler2Wins,
+    Draw,
+}
+
+impl BattleOutcome {
+    pub fn loss_for(side: usize) -> Self {
+        if side == 0 {
+            BattleOutcome::Battler2Wins
+        } else {
+            BattleOutcome::Battler1Wins
+        }
+    }
+}
+
+pub fn action_priority(current: &Current, action: Action) -> i16 {
+    // Forfeiting and switching happen before any move, regardless of speed.
+    match action {
+        Action::Forfeit => 9,
+        Action::Switch(_) => 8,
+        Action::Move(slot) => current.borrow().chosen_move(slot)
+            .map_or(0, |mov| mov.priority as i16),
+    }
+}
+
+pub fn goes_first<R: rand::Rng>(
+    first: &Current, first_action: Action,
+    second: &Current, second_action: Action, rng: &mut R
+) -> bool {
+    let first_key = (action_priority(first, first_action),
+        first.borrow().speed());
+    let second_key = (action_priority(second, second_action),
+        second.borrow().speed());
+    match first_key.cmp(&second_key) {
+        Ordering::Greater => true,
+        Ordering::Less => false,
+        Ordering::Equal => rng.gen(),
+    }
+}
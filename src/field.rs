@@ -0,0 +1,304 @@
+use crate::ailments::BenchAilment;
+use crate::battle::{BattlePokemon, DamageContext};
+use crate::hooks::{
+    self, DamageHook, HookKey, Hooks, StatHook, StatusHook};
+use crate::shared::Shared;
+use vdex::Ability;
+use vdex::moves::{DamageClass, MoveId};
+use vdex::Stat;
+use vdex::Type;
+
+pub const WEATHER_TURNS: u8 = 5;
+pub const SCREEN_TURNS: u8 = 5;
+pub const TAILWIND_TURNS: u8 = 3;
+
+pub const THUNDER: MoveId = MoveId(86);
+pub const BLIZZARD: MoveId = MoveId(58);
+pub const WEATHER_HEALING_MOVES: [MoveId; 3] =
+    [MoveId(233), MoveId(234), MoveId(235)];
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Weather {
+    Clear,
+    Rain,
+    Sun,
+    Sandstorm,
+    Hail,
+}
+
+pub const WEATHERS: [Weather; 5] = [
+    Weather::Clear,
+    Weather::Rain,
+    Weather::Sun,
+    Weather::Sandstorm,
+    Weather::Hail,
+];
+
+impl Weather {
+    pub fn from_move(mov: MoveId) -> Option<Weather> {
+        match mov {
+            MoveId(239) => Some(Weather::Rain),
+            MoveId(240) => Some(Weather::Sun),
+            MoveId(200) => Some(Weather::Sandstorm),
+            MoveId(257) => Some(Weather::Hail),
+            _ => None,
+        }
+    }
+
+    pub fn from_ability(ability: Ability) -> Option<Weather> {
+        match ability {
+            Ability::Drizzle => Some(Weather::Rain),
+            Ability::Drought => Some(Weather::Sun),
+            Ability::SandStream => Some(Weather::Sandstorm),
+            Ability::SnowWarning => Some(Weather::Hail),
+            _ => None,
+        }
+    }
+
+    pub fn damages(self, pokemon: &BattlePokemon) -> bool {
+        match self {
+            Weather::Sandstorm => !(pokemon.types.contains(Type::Rock)
+                || pokemon.types.contains(Type::Ground)
+                || pokemon.types.contains(Type::Steel)
+                || pokemon.overlay.ability == Ability::SandVeil),
+            Weather::Hail => !(pokemon.types.contains(Type::Ice)
+                || pokemon.overlay.ability == Ability::IceBody
+                || pokemon.overlay.ability == Ability::SnowCloak),
+            _ => false,
+        }
+    }
+
+    pub fn healing(self, max_hp: u16) -> u16 {
+        // Synthesis, Moonlight and Morning Sun.
+        let healing = match self {
+            Weather::Clear => max_hp / 2,
+            Weather::Sun => max_hp * 2 / 3,
+            _ => max_hp / 4,
+        };
+        healing.max(1)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SideCondition {
+    Reflect,
+    LightScreen,
+    Mist,
+    Safeguard,
+    Tailwind,
+    LuckyChant,
+}
+
+pub const SIDE_CONDITIONS: [SideCondition; 6] = [
+    SideCondition::Reflect,
+    SideCondition::LightScreen,
+    SideCondition::Mist,
+    SideCondition::Safeguard,
+    SideCondition::Tailwind,
+    SideCondition::LuckyChant,
+];
+
+impl SideCondition {
+    pub fn from_move(mov: MoveId) -> Option<SideCondition> {
+        // Moves whose effects are not named in the older effect list.
+        match mov {
+            MoveId(218) => Some(SideCondition::Safeguard),
+            MoveId(365) => Some(SideCondition::Tailwind),
+            MoveId(380) => Some(SideCondition::LuckyChant),
+            _ => None,
+        }
+    }
+
+    pub fn turns(self) -> u8 {
+        match self {
+            SideCondition::Tailwind => TAILWIND_TURNS,
+            _ => SCREEN_TURNS,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Hazard {
+    Spikes,
+    ToxicSpikes,
+    StealthRock,
+}
+
+pub const HAZARDS: [Hazard; 3] = [
+    Hazard::Spikes,
+    Hazard::ToxicSpikes,
+    Hazard::StealthRock,
+];
+
+pub const RAPID_SPIN: MoveId = MoveId(228);
+
+impl Hazard {
+    pub fn from_move(mov: MoveId) -> Option<Hazard> {
+        match mov {
+            MoveId(190) => Some(Hazard::Spikes),
+            MoveId(389) => Some(Hazard::ToxicSpikes),
+            MoveId(445) => Some(Hazard::StealthRock),
+            _ => None,
+        }
+    }
+
+    pub fn max_layers(self) -> u8 {
+        match self {
+            Hazard::Spikes => 3,
+            Hazard::ToxicSpikes => 2,
+            Hazard::StealthRock => 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub weather: Weather,
+    // Weather set by an ability lasts until something replaces it.
+    pub weather_turns: Option<u8>,
+    pub side_conditions: [[u8; SIDE_CONDITIONS.len()]; 2],
+    pub hazards: [[u8; HAZARDS.len()]; 2],
+    pub doubles: bool,
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Self {
+            weather: Weather::Clear,
+            weather_turns: None,
+            side_conditions: [[0; SIDE_CONDITIONS.len()]; 2],
+            hazards: [[0; HAZARDS.len()]; 2],
+            doubles: false,
+        }
+    }
+}
+
+impl Field {
+    pub fn side_turns(
+        &mut self, side: usize, condition: SideCondition
+    ) -> &mut u8 {
+        &mut self.side_conditions[side][condition as usize]
+    }
+
+    pub fn has(&self, side: usize, condition: SideCondition) -> bool {
+        self.side_conditions[side][condition as usize] > 0
+    }
+
+    pub fn layers(&self, side: usize, hazard: Hazard) -> u8 {
+        self.hazards[side][hazard as usize]
+    }
+
+    pub fn tick_sides(&mut self) -> Vec<(usize, SideCondition)> {
+        let mut ended = Vec::new();
+        for (side, turns) in self.side_conditions.iter_mut().enumerate() {
+            let conditions = SIDE_CONDITIONS.iter().zip(turns.iter_mut());
+            for (condition, turns) in conditions {
+                if *turns > 0 {
+                    *turns -= 1;
+                    if *turns == 0 {
+                        ended.push((side, *condition));
+                    }
+                }
+            }
+        }
+        ended
+    }
+
+    pub fn tick_weather(&mut self) -> Option<Weather> {
+        let ended = match self.weather_turns {
+            Some(turns) if turns <= 1 => Some(self.weather),
+            Some(turns) => {
+                self.weather_turns = Some(turns - 1);
+                None
+            },
+            None => None,
+        };
+        if ended.is_some() {
+            self.weather = Weather::Clear;
+            self.weather_turns = None;
+        }
+        ended
+    }
+}
+
+fn weather(user: &BattlePokemon) -> Weather {
+    user.field.borrow().weather
+}
+
+fn weather_damage(
+    ctx: &DamageContext, user: &BattlePokemon, _: &BattlePokemon
+) -> f64 {
+    match (weather(user), ctx.typ) {
+        (Weather::Rain, Some(Type::Water)) | (Weather::Sun, Some(Type::Fire))
+            => 1.5,
+        (Weather::Rain, Some(Type::Fire)) | (Weather::Sun, Some(Type::Water))
+            => 0.5,
+        _ => 1.0,
+    }
+}
+
+fn sandstorm_defense(
+    ctx: &DamageContext, user: &BattlePokemon, target: &BattlePokemon
+) -> f64 {
+    match (weather(user), ctx.class) {
+        (Weather::Sandstorm, DamageClass::Special)
+            if target.types.contains(Type::Rock) => 1.5,
+        _ => 1.0,
+    }
+}
+
+fn weather_accuracy(
+    ctx: &DamageContext, user: &BattlePokemon, _: &BattlePokemon
+) -> f64 {
+    match (weather(user), ctx.mov.id) {
+        (Weather::Rain, THUNDER) | (Weather::Hail, BLIZZARD)
+            => std::f64::INFINITY,
+        (Weather::Sun, THUNDER) => 50.0 / 70.0,
+        _ => 1.0,
+    }
+}
+
+fn screens(
+    ctx: &DamageContext, _: &BattlePokemon, target: &BattlePokemon
+) -> f64 {
+    let condition = match ctx.class {
+        DamageClass::Physical => SideCondition::Reflect,
+        DamageClass::Special => SideCondition::LightScreen,
+        _ => return 1.0,
+    };
+    if !target.side_has(condition) || ctx.critical_against(target) {
+        1.0
+    } else if target.field.borrow().doubles {
+        2.0 / 3.0
+    } else {
+        0.5
+    }
+}
+
+fn mist(pokemon: &BattlePokemon, _: Stat) -> bool {
+    pokemon.side_has(SideCondition::Mist)
+}
+
+fn safeguard(pokemon: &BattlePokemon, _: &BenchAilment) -> bool {
+    pokemon.side_has(SideCondition::Safeguard)
+}
+
+pub fn install(hooks: &Hooks) {
+    let key = HookKey::new_engine(0, hooks::ENGINE_WEATHER, 0);
+    hooks.user_damage_modifiers.battle.borrow_mut()
+        .insert(key, DamageHook(weather_damage));
+    hooks.defense_modifiers.battle.borrow_mut()
+        .insert(key, DamageHook(sandstorm_defense));
+    hooks.user_accuracy_modifiers.battle.borrow_mut()
+        .insert(key, DamageHook(weather_accuracy));
+    let key = HookKey::new_engine(0, hooks::ENGINE_SIDE_CONDITIONS, 0);
+    hooks.target_damage_modifiers.battle.borrow_mut()
+        .insert(key, DamageHook(screens));
+    hooks.stat_drop_blocks.battle.borrow_mut().insert(key, StatHook(mist));
+    hooks.status_immunities.battle.borrow_mut()
+        .insert(key, StatusHook(safeguard));
+}
+
+pub fn new_field() -> Shared<Field> {
+    Shared::new(Field::default())
+}
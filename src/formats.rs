@@ -1,9 +1,20 @@
 use crate::battle;
-use crate::hooks::Hooks;
+use crate::events::{self, Event, Log};
+use crate::exec::moves::{STRUGGLE, execute_move, is_implemented};
+use crate::exec::premove::{MoveBlock, check_move};
+use crate::exec::residual;
+use crate::exec::turn::{
+    Action, ActionError, BattleOutcome, Choice, goes_first};
+use crate::field::{self, Field};
+use crate::hooks::{Hooks, Trigger, TriggerContext};
+use crate::replay::Replay;
+use crate::rules::{Clauses, RuleViolation, Ruleset};
 use crate::team::Team;
-use std::cell::RefCell;
-use std::rc::Rc;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use crate::shared::Shared;
 use vdex::Enum;
+use vdex::moves::Effect;
 use enum_repr::EnumRepr;
 
 #[EnumRepr(type = "u8")]
@@ -15,6 +26,10 @@ pub enum AbsoluteTarget {
 }
 
 impl AbsoluteTarget {
+    pub fn side(self) -> usize {
+        (self.repr() >> 1) as usize
+    }
+
     pub fn relative(self, user: &AbsoluteTarget) -> RelativeTarget {
         RelativeTarget::from_repr(self.repr() ^ user.repr()).unwrap()
     }
@@ -40,32 +55,420 @@ pub struct SingleBattler {
 }
 
 impl SingleBattler {
-    pub fn new(position: AbsoluteTarget, team: &Team, hooks: &Hooks) -> Self {
+    pub fn new(
+        position: AbsoluteTarget, team: &Team, hooks: &Hooks, log: &Log,
+        field: &Shared<Field>
+    ) -> Self {
         let mut bench = Vec::new();
         for member in team {
-            bench.push(Rc::new(RefCell::new(
-                battle::BenchPokemon::new(member))));
+            bench.push(Shared::new(battle::BenchPokemon::new(member)));
         }
-        let current = Rc::new(RefCell::new(
-            battle::BattlePokemon::new(position, 0, &bench[0], hooks)));
+        let current = Shared::new(battle::BattlePokemon::new(
+            position, &bench, 0, hooks, log, field));
         Self { bench, current }
     }
+
+    pub fn check_action(&self, action: Action) -> Result<(), ActionError> {
+        match action {
+            Action::Move(slot) => {
+                if slot >= 4 {
+                    return Err(ActionError::InvalidSlot);
+                }
+                let current = self.current.borrow();
+                // With no PP left in any move, any slot picks Struggle.
+                if current.must_struggle() {
+                    return Ok(());
+                }
+                let mov = match current.overlay.moves[slot as usize] {
+                    Some(mov) => mov,
+                    None => return Err(ActionError::EmptySlot),
+                };
+                if current.perm.borrow().pp[slot as usize] == 0 {
+                    Err(ActionError::NoPP)
+                } else if !is_implemented(mov) {
+                    Err(ActionError::UnimplementedMove)
+                } else if current.choice_lock.map_or(false, |lock| lock != slot)
+                {
+                    Err(ActionError::ChoiceLocked)
+                } else {
+                    Ok(())
+                }
+            },
+            Action::Switch(index) => self.check_switch(index),
+            Action::Forfeit => Ok(()),
+        }
+    }
+
+    pub fn is_defeated(&self) -> bool {
+        self.bench.iter().all(|perm| perm.borrow().hp == 0)
+    }
+
+    pub fn needs_replacement(&self) -> bool {
+        self.current.borrow().is_fainted() && !self.is_defeated()
+    }
+
+    pub fn switch_options(&self) -> Vec<usize> {
+        (0..self.bench.len())
+            .filter(|index| self.check_switch(*index).is_ok()).collect()
+    }
+
+    pub fn check_switch(&self, index: usize) -> Result<(), ActionError> {
+        if index >= self.bench.len() {
+            Err(ActionError::InvalidSwitch)
+        } else if index == self.current.borrow().index {
+            Err(ActionError::AlreadyActive)
+        } else if self.bench[index].borrow().hp == 0 {
+            Err(ActionError::Fainted)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn switch(&mut self, index: usize) -> Result<(), ActionError> {
+        self.check_switch(index)?;
+        let mut current = self.current.borrow_mut();
+        current.teardown();
+        let incoming = battle::BattlePokemon::new(current.position,
+            &self.bench, index, &current.hooks, &current.log, &current.field);
+        *current = incoming;
+        current.log(Event::Switch { target: current.position, index });
+        current.switch_in();
+        Ok(())
+    }
+}
+
+fn fire<R: rand::Rng>(
+    trigger: Trigger, current: &battle::Current, foe: &battle::Current,
+    rng: &mut R
+) {
+    if !current.borrow().is_fainted() {
+        TriggerContext::new(trigger, current, Some(foe), rng).fire();
+    }
 }
 
 pub struct SingleBattle {
     pub hooks: Hooks,
+    pub rules: Ruleset,
     pub battler1: SingleBattler,
     pub battler2: SingleBattler,
+    pub log: Log,
+    pub field: Shared<Field>,
+    pub seed: u64,
+    pub rng: ChaChaRng,
+    pub record: Vec<Choice>,
+    pub turn: u16,
+    pub outcome: Option<BattleOutcome>,
 }
 
 impl SingleBattle {
     pub fn new(team1: &Team, team2: &Team) -> Self {
+        SingleBattle::with_rules(team1, team2, Ruleset::default())
+    }
+
+    pub fn with_rules(team1: &Team, team2: &Team, rules: Ruleset) -> Self {
+        SingleBattle::new_seeded(team1, team2, rules, rand::random())
+    }
+
+    // Checks both teams against the rules first. The other constructors trust
+    // their teams, and a team that breaks the rules (an empty one, say) may
+    // panic partway through the battle.
+    pub fn new_validated(
+        team1: &Team, team2: &Team, rules: Ruleset, seed: u64
+    ) -> Result<Self, Vec<RuleViolation>> {
+        let mut violations = rules.validate(team1, false);
+        violations.extend(rules.validate(team2, false));
+        if violations.is_empty() {
+            Ok(SingleBattle::new_seeded(team1, team2, rules, seed))
+        } else {
+            Err(violations)
+        }
+    }
+
+    pub fn new_seeded(
+        team1: &Team, team2: &Team, rules: Ruleset, seed: u64
+    ) -> Self {
         let hooks = Hooks::new_battle();
-        let battler1 = SingleBattler::new(
-            AbsoluteTarget::Battler1_1, team1, &hooks);
-        let battler2 = SingleBattler::new(
-            AbsoluteTarget::Battler2_1, team2, &hooks);
-        Self { hooks, battler1, battler2 }
+        rules.install(&hooks);
+        field::install(&hooks);
+        let log = events::new_log();
+        let field = field::new_field();
+        let battler1 = SingleBattler::new(AbsoluteTarget::Battler1_1,
+            &rules.prepare(team1), &hooks, &log, &field);
+        let battler2 = SingleBattler::new(AbsoluteTarget::Battler2_1,
+            &rules.prepare(team2), &hooks, &log, &field);
+        let mut battle = Self {
+            hooks,
+            rules,
+            battler1,
+            battler2,
+            log,
+            field,
+            seed,
+            rng: ChaChaRng::seed_from_u64(seed),
+            record: Vec::new(),
+            turn: 0,
+            outcome: None,
+        };
+        let mut rng = battle.rng.clone();
+        let order = battle.speed_order(&mut rng);
+        for (current, _) in order.iter() {
+            current.borrow_mut().switch_in();
+        }
+        for (current, foe) in order.iter() {
+            fire(Trigger::OnSwitchIn, current, foe, &mut rng);
+        }
+        battle.rng = rng;
+        battle
+    }
+
+    pub fn team(&self, side: usize) -> Team {
+        self.battler(side).bench.iter()
+            .map(|perm| perm.borrow().base.clone()).collect()
+    }
+
+    pub fn replay(&self) -> Replay {
+        Replay {
+            seed: self.seed,
+            clauses: self.rules.clauses,
+            team1: self.team(0),
+            team2: self.team(1),
+            choices: self.record.clone(),
+        }
+    }
+
+    pub fn drain_events(&self) -> Vec<Event> {
+        self.log.borrow_mut().drain(..).collect()
+    }
+
+    pub fn battler(&self, side: usize) -> &SingleBattler {
+        if side == 0 {
+            &self.battler1
+        } else {
+            &self.battler2
+        }
+    }
+
+    pub fn battler_mut(&mut self, side: usize) -> &mut SingleBattler {
+        if side == 0 {
+            &mut self.battler1
+        } else {
+            &mut self.battler2
+        }
+    }
+
+    pub fn pending_replacements(&self) -> [bool; 2] {
+        [self.battler1.needs_replacement(), self.battler2.needs_replacement()]
+    }
+
+    pub fn replace(
+        &mut self, side: usize, index: usize
+    ) -> Result<(), ActionError> {
+        if !self.battler(side).needs_replacement() {
+            return Err(ActionError::NoReplacementNeeded);
+        }
+        let mut rng = self.rng.clone();
+        self.switch(side, index, &mut rng)?;
+        self.rng = rng;
+        self.record.push(Choice::Replace(side, index));
+        self.check_faints(None);
+        Ok(())
+    }
+
+    fn switch<R: rand::Rng>(
+        &mut self, side: usize, index: usize, rng: &mut R
+    ) -> Result<(), ActionError> {
+        self.battler(side).check_switch(index)?;
+        let foe = self.battler(1 - side).current.clone();
+        fire(Trigger::OnSwitchOut, &self.battler(side).current, &foe, rng);
+        self.battler_mut(side).switch(index)?;
+        fire(Trigger::OnSwitchIn, &self.battler(side).current, &foe, rng);
+        Ok(())
+    }
+
+    fn end_by_forfeit(&mut self, side: usize) -> BattleOutcome {
+        let outcome = BattleOutcome::loss_for(side);
+        let mut log = self.log.borrow_mut();
+        log.push(Event::Forfeit { side });
+        log.push(Event::End(outcome));
+        outcome
+    }
+
+    pub fn forfeit(&mut self, side: usize) -> BattleOutcome {
+        // For forfeiting outside of a turn, e.g. when a player disconnects.
+        if let Some(outcome) = self.outcome {
+            return outcome;
+        }
+        let outcome = self.end_by_forfeit(side);
+        self.outcome = Some(outcome);
+        self.record.push(Choice::Forfeit(side));
+        outcome
+    }
+
+    fn check_faints(
+        &mut self, attacker: Option<(usize, u8)>
+    ) -> Option<BattleOutcome> {
+        let mut self_ko = None;
+        if let Some((side, slot)) = attacker {
+            let user = self.battler(side).current.clone();
+            let target = self.battler(1 - side).current.clone();
+            let bonded = {
+                let target = target.borrow();
+                target.is_fainted() && target.destiny_bond
+            };
+            if bonded && !user.borrow().is_fainted() {
+                user.borrow_mut().faint();
+                self_ko = Some(1 - side);
+            } else if let Some(mov) = user.borrow().chosen_move(slot) {
+                if let Effect::FaintUser = mov.effect {
+                    self_ko = Some(side);
+                }
+            }
+        }
+        self.outcome = match (self.battler1.is_defeated(),
+            self.battler2.is_defeated())
+        {
+            (true, true) => match self_ko {
+                Some(side) if self.rules.clauses.contains(Clauses::SELF_KO)
+                    => Some(BattleOutcome::loss_for(side)),
+                _ => Some(BattleOutcome::Draw),
+            },
+            (true, false) => Some(BattleOutcome::Battler2Wins),
+            (false, true) => Some(BattleOutcome::Battler1Wins),
+            (false, false) => None,
+        };
+        if let Some(outcome) = self.outcome {
+            self.log.borrow_mut().push(Event::End(outcome));
+        }
+        self.outcome
+    }
+
+    pub fn run_turn(
+        &mut self, action1: Action, action2: Action
+    ) -> Result<Option<BattleOutcome>, ActionError> {
+        let mut rng = self.rng.clone();
+        let result = self.play_turn(action1, action2, &mut rng);
+        self.rng = rng;
+        if result.is_ok() {
+            self.record.push(Choice::Turn(action1, action2));
+        }
+        result
+    }
+
+    fn play_turn<R: rand::Rng>(
+        &mut self, action1: Action, action2: Action, rng: &mut R
+    ) -> Result<Option<BattleOutcome>, ActionError> {
+        if self.outcome.is_some() {
+            return Err(ActionError::BattleOver);
+        }
+        if self.pending_replacements().iter().any(|pending| *pending) {
+            return Err(ActionError::ReplacementPending);
+        }
+        self.battler1.check_action(action1)?;
+        self.battler2.check_action(action2)?;
+        self.turn += 1;
+        self.log.borrow_mut().push(Event::Turn(self.turn));
+        let order = if goes_first(&self.battler1.current, action1,
+            &self.battler2.current, action2, rng)
+        {
+            [(0, action1), (1, action2)]
+        } else {
+            [(1, action2), (0, action1)]
+        };
+        for &(side, action) in order.iter() {
+            if self.battler(side).current.borrow().is_fainted() {
+                continue;
+            }
+            match action {
+                Action::Forfeit => {
+                    self.outcome = Some(self.end_by_forfeit(side));
+                    return Ok(self.outcome);
+                },
+                Action::Switch(index) => {
+                    self.switch(side, index, rng)?;
+                    if self.check_faints(None).is_some() {
+                        return Ok(self.outcome);
+                    }
+                },
+                Action::Move(slot) => {
+                    let _ = self.use_move(side, slot, rng);
+                    if self.check_faints(Some((side, slot))).is_some() {
+                        return Ok(self.outcome);
+                    }
+                },
+            }
+        }
+        self.end_turn(rng);
+        Ok(self.check_faints(None))
+    }
+
+    pub fn use_move<R: rand::Rng>(
+        &self, side: usize, slot: u8, rng: &mut R
+    ) -> Result<bool, MoveBlock> {
+        let user = self.battler(side).current.clone();
+        user.borrow_mut().destiny_bond = false;
+        user.borrow_mut().moved = true;
+        let mov = user.borrow().chosen_move(slot).unwrap();
+        // Struggle isn't in a slot, so it uses no PP and can't be locked in.
+        let slot = if mov.id == STRUGGLE { 4 } else { slot };
+        if let Err(reason) = check_move(&user, mov, rng) {
+            let position = user.borrow().position;
+            user.borrow().log(Event::MoveBlocked { user: position, reason });
+            return Err(reason);
+        }
+        let foe = &self.battler(1 - side).current;
+        let mut ctx = TriggerContext::new(
+            Trigger::BeforeMove, &user, Some(foe), &mut *rng);
+        ctx.mov = Some(mov);
+        ctx.fire();
+        let held = user.borrow().held_item();
+        if slot < 4 && held.map_or(false, |held| held.is_choice()) {
+            user.borrow_mut().choice_lock.get_or_insert(slot);
+        }
+        Ok(execute_move(&user, slot, mov,
+            |tgts| self.resolve_targets(tgts), rng))
+    }
+
+    fn speed_order<R: rand::Rng>(
+        &self, rng: &mut R
+    ) -> [(&battle::Current, &battle::Current); 2] {
+        let first = &self.battler1.current;
+        let second = &self.battler2.current;
+        let first_speed = first.borrow().speed();
+        let second_speed = second.borrow().speed();
+        if first_speed > second_speed
+            || (first_speed == second_speed && rng.gen())
+        {
+            [(first, second), (second, first)]
+        } else {
+            [(second, first), (first, second)]
+        }
+    }
+
+    pub fn end_turn<R: rand::Rng>(&mut self, rng: &mut R) {
+        let ended = self.field.borrow_mut().tick_weather();
+        if let Some(weather) = ended {
+            self.log.borrow_mut().push(Event::WeatherEnded { weather });
+        }
+        let ended = self.field.borrow_mut().tick_sides();
+        for (side, condition) in ended {
+            self.log.borrow_mut()
+                .push(Event::SideConditionEnded { side, condition });
+        }
+        let order = self.speed_order(rng);
+        for kind in residual::ORDER.iter() {
+            for (current, foe) in order.iter() {
+                residual::apply(*kind, current, foe);
+            }
+        }
+        for (current, foe) in order.iter() {
+            fire(Trigger::EndOfTurn, current, foe, rng);
+        }
+        for (current, _) in order.iter() {
+            let mut current = current.borrow_mut();
+            current.tick_embargo();
+            current.moved = false;
+            current.flinched = false;
+        }
     }
 
     pub fn resolve_targets(
@@ -73,12 +476,13 @@ impl SingleBattle {
     ) -> Vec<battle::Current> {
         let mut currents = Vec::new();
         for target in targets {
-            match target {
-                AbsoluteTarget::Battler1_1
-                    => currents.push(self.battler1.current.clone()),
-                AbsoluteTarget::Battler2_1
-                    => currents.push(self.battler2.current.clone()),
-                _ => (),
+            let current = match target {
+                AbsoluteTarget::Battler1_1 => &self.battler1.current,
+                AbsoluteTarget::Battler2_1 => &self.battler2.current,
+                _ => continue,
+            };
+            if !current.borrow().is_fainted() {
+                currents.push(current.clone());
             }
         }
         currents
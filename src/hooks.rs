@@ -1,12 +1,18 @@
+use crate::ailments;
 use crate::battle;
+use crate::events::Event;
 use crate::formats::RelativeTarget;
-use std::cell::RefCell;
+use crate::shared::Shared;
 use std::collections::BTreeMap;
 use std::fmt;
-use std::rc::Rc;
 use vdex::Ability;
 use vdex::moves;
 use vdex::items;
+use vdex::Stat;
+
+pub const ENGINE_SLEEP_CLAUSE: u16 = 1;
+pub const ENGINE_WEATHER: u16 = 2;
+pub const ENGINE_SIDE_CONDITIONS: u16 = 3;
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
 pub enum HookSource {
@@ -43,25 +49,36 @@ impl HookKey {
 
 #[derive(Clone)]
 pub struct HookMap<T: Clone> {
-    pub battle: Rc<RefCell<BTreeMap<HookKey, T>>>,
+    pub battle: Shared<BTreeMap<HookKey, T>>,
     pub overlay: BTreeMap<HookKey, T>,
 }
 
 impl<T: Clone> HookMap<T> {
     pub fn new_battle() -> Self {
         Self {
-            battle: Rc::new(RefCell::new(BTreeMap::new())),
+            battle: Shared::new(BTreeMap::new()),
             overlay: BTreeMap::new(),
         }
     }
 
-    pub fn new_overlay(battle: &Rc<RefCell<BTreeMap<HookKey, T>>>) -> Self {
+    pub fn new_overlay(battle: &Shared<BTreeMap<HookKey, T>>) -> Self {
         Self {
             battle: battle.clone(),
             overlay: BTreeMap::new(),
         }
     }
 
+    pub fn clear_overlay(&mut self) {
+        self.overlay.clear();
+    }
+
+    pub fn clear_item_overlay(&mut self) {
+        self.overlay.retain(|key, _| match key.source {
+            HookSource::Item(_) => false,
+            _ => true,
+        });
+    }
+
     pub fn fold<A, F>(
         &self, init: A, mut func: F
     ) -> A where F: FnMut(A, &T) -> A {
@@ -101,8 +118,12 @@ impl<T: Clone> fmt::Debug for HookMap<T> {
     }
 }
 
+// Damage hooks receive the user and target already borrowed, as the caller
+// holds their locks while folding the hooks.
 #[derive(Copy)]
-pub struct DamageHook(pub fn(&battle::DamageContext) -> f64);
+pub struct DamageHook(pub fn(
+    &battle::DamageContext, &battle::BattlePokemon, &battle::BattlePokemon
+) -> f64);
 
 impl Clone for DamageHook {
     fn clone(&self) -> Self {
@@ -110,6 +131,25 @@ impl Clone for DamageHook {
     }
 }
 
+#[derive(Copy)]
+pub struct StatusHook(
+    pub fn(&battle::BattlePokemon, &ailments::BenchAilment) -> bool);
+
+impl Clone for StatusHook {
+    fn clone(&self) -> Self {
+        StatusHook(self.0)
+    }
+}
+
+#[derive(Copy)]
+pub struct StatHook(pub fn(&battle::BattlePokemon, Stat) -> bool);
+
+impl Clone for StatHook {
+    fn clone(&self) -> Self {
+        StatHook(self.0)
+    }
+}
+
 #[derive(Copy)]
 pub struct TargetingHook(
     pub fn(&battle::Current, moves::Target) -> RelativeTarget);
@@ -122,7 +162,7 @@ impl Clone for TargetingHook {
 
 #[derive(Clone)]
 pub struct TargetingPair(
-    pub Rc<RefCell<TargetingHook>>, pub Option<TargetingHook>);
+    pub Shared<TargetingHook>, pub Option<TargetingHook>);
 
 impl TargetingPair {
     pub fn call(
@@ -142,6 +182,81 @@ impl fmt::Debug for TargetingPair {
     }
 }
 
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Trigger {
+    OnSwitchIn,
+    OnSwitchOut,
+    BeforeMove,
+    AfterMoveHit,
+    OnContact,
+    OnDamaged,
+    OnStatusInflicted,
+    EndOfTurn,
+}
+
+pub const TRIGGERS: [Trigger; 8] = [
+    Trigger::OnSwitchIn,
+    Trigger::OnSwitchOut,
+    Trigger::BeforeMove,
+    Trigger::AfterMoveHit,
+    Trigger::OnContact,
+    Trigger::OnDamaged,
+    Trigger::OnStatusInflicted,
+    Trigger::EndOfTurn,
+];
+
+pub struct TriggerContext<'a> {
+    pub trigger: Trigger,
+    pub current: &'a battle::Current,
+    pub other: Option<&'a battle::Current>,
+    pub mov: Option<&'static moves::Move>,
+    pub damage: u16,
+    pub status: Option<ailments::BenchAilment>,
+    pub rng: &'a mut dyn rand::RngCore,
+}
+
+impl<'a> TriggerContext<'a> {
+    pub fn new(
+        trigger: Trigger, current: &'a battle::Current,
+        other: Option<&'a battle::Current>, rng: &'a mut dyn rand::RngCore
+    ) -> Self {
+        Self {
+            trigger,
+            current,
+            other,
+            mov: None,
+            damage: 0,
+            status: None,
+            rng,
+        }
+    }
+
+    pub fn emit(&self, event: Event) {
+        self.current.borrow().log(event);
+    }
+
+    pub fn fire(&mut self) {
+        // Collected first so that hooks may borrow the Pokémon mutably.
+        let hooks = self.current.borrow().hooks.triggers(self.trigger)
+            .fold(Vec::new(), |mut hooks, hook| {
+                hooks.push(*hook);
+                hooks
+            });
+        for hook in hooks {
+            hook.0(self);
+        }
+    }
+}
+
+#[derive(Copy)]
+pub struct TriggerHook(pub fn(&mut TriggerContext));
+
+impl Clone for TriggerHook {
+    fn clone(&self) -> Self {
+        TriggerHook(self.0)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Hooks {
     pub targeting: TargetingPair,
@@ -153,12 +268,22 @@ pub struct Hooks {
     pub defense_modifiers: HookMap<DamageHook>,
     pub user_damage_modifiers: HookMap<DamageHook>,
     pub target_damage_modifiers: HookMap<DamageHook>,
+    pub status_immunities: HookMap<StatusHook>,
+    pub stat_drop_blocks: HookMap<StatHook>,
+    pub on_switch_in: HookMap<TriggerHook>,
+    pub on_switch_out: HookMap<TriggerHook>,
+    pub before_move: HookMap<TriggerHook>,
+    pub after_move_hit: HookMap<TriggerHook>,
+    pub on_contact: HookMap<TriggerHook>,
+    pub on_damaged: HookMap<TriggerHook>,
+    pub on_status_inflicted: HookMap<TriggerHook>,
+    pub end_of_turn: HookMap<TriggerHook>,
 }
 
 impl Hooks {
     pub fn new_battle() -> Self {
         Self {
-            targeting: TargetingPair(Rc::new(RefCell::new(TargetingHook(|_, mtgt| {
+            targeting: TargetingPair(Shared::new(TargetingHook(|_, mtgt| {
                 match mtgt {
                     moves::Target::SpecificMove
                         => panic!("Not implemented yet!"),
@@ -170,7 +295,7 @@ impl Hooks {
                         => RelativeTarget::User,
                     _ => panic!("Only call targeting if the target can vary!"),
                 }
-            }))), None),
+            })), None),
             user_accuracy_modifiers: HookMap::new_battle(),
             target_accuracy_modifiers: HookMap::new_battle(),
             critical_cancels: HookMap::new_battle(),
@@ -179,6 +304,16 @@ impl Hooks {
             defense_modifiers: HookMap::new_battle(),
             user_damage_modifiers: HookMap::new_battle(),
             target_damage_modifiers: HookMap::new_battle(),
+            status_immunities: HookMap::new_battle(),
+            stat_drop_blocks: HookMap::new_battle(),
+            on_switch_in: HookMap::new_battle(),
+            on_switch_out: HookMap::new_battle(),
+            before_move: HookMap::new_battle(),
+            after_move_hit: HookMap::new_battle(),
+            on_contact: HookMap::new_battle(),
+            on_damaged: HookMap::new_battle(),
+            on_status_inflicted: HookMap::new_battle(),
+            end_of_turn: HookMap::new_battle(),
         }
     }
 
@@ -201,6 +336,81 @@ impl Hooks {
                 HookMap::new_overlay(&battle.user_damage_modifiers.battle),
             target_damage_modifiers:
                 HookMap::new_overlay(&battle.target_damage_modifiers.battle),
+            status_immunities:
+                HookMap::new_overlay(&battle.status_immunities.battle),
+            stat_drop_blocks:
+                HookMap::new_overlay(&battle.stat_drop_blocks.battle),
+            on_switch_in: HookMap::new_overlay(&battle.on_switch_in.battle),
+            on_switch_out: HookMap::new_overlay(&battle.on_switch_out.battle),
+            before_move: HookMap::new_overlay(&battle.before_move.battle),
+            after_move_hit:
+                HookMap::new_overlay(&battle.after_move_hit.battle),
+            on_contact: HookMap::new_overlay(&battle.on_contact.battle),
+            on_damaged: HookMap::new_overlay(&battle.on_damaged.battle),
+            on_status_inflicted:
+                HookMap::new_overlay(&battle.on_status_inflicted.battle),
+            end_of_turn: HookMap::new_overlay(&battle.end_of_turn.battle),
+        }
+    }
+
+    pub fn clear_overlay(&mut self) {
+        self.targeting.1 = None;
+        self.user_accuracy_modifiers.clear_overlay();
+        self.target_accuracy_modifiers.clear_overlay();
+        self.critical_cancels.clear_overlay();
+        self.power_modifiers.clear_overlay();
+        self.attack_modifiers.clear_overlay();
+        self.defense_modifiers.clear_overlay();
+        self.user_damage_modifiers.clear_overlay();
+        self.target_damage_modifiers.clear_overlay();
+        self.status_immunities.clear_overlay();
+        self.stat_drop_blocks.clear_overlay();
+        for trigger in TRIGGERS.iter() {
+            self.triggers_mut(*trigger).clear_overlay();
+        }
+    }
+
+    pub fn clear_item_overlay(&mut self) {
+        self.user_accuracy_modifiers.clear_item_overlay();
+        self.target_accuracy_modifiers.clear_item_overlay();
+        self.critical_cancels.clear_item_overlay();
+        self.power_modifiers.clear_item_overlay();
+        self.attack_modifiers.clear_item_overlay();
+        self.defense_modifiers.clear_item_overlay();
+        self.user_damage_modifiers.clear_item_overlay();
+        self.target_damage_modifiers.clear_item_overlay();
+        self.status_immunities.clear_item_overlay();
+        self.stat_drop_blocks.clear_item_overlay();
+        for trigger in TRIGGERS.iter() {
+            self.triggers_mut(*trigger).clear_item_overlay();
+        }
+    }
+
+    pub fn triggers(&self, trigger: Trigger) -> &HookMap<TriggerHook> {
+        match trigger {
+            Trigger::OnSwitchIn => &self.on_switch_in,
+            Trigger::OnSwitchOut => &self.on_switch_out,
+            Trigger::BeforeMove => &self.before_move,
+            Trigger::AfterMoveHit => &self.after_move_hit,
+            Trigger::OnContact => &self.on_contact,
+            Trigger::OnDamaged => &self.on_damaged,
+            Trigger::OnStatusInflicted => &self.on_status_inflicted,
+            Trigger::EndOfTurn => &self.end_of_turn,
+        }
+    }
+
+    pub fn triggers_mut(
+        &mut self, trigger: Trigger
+    ) -> &mut HookMap<TriggerHook> {
+        match trigger {
+            Trigger::OnSwitchIn => &mut self.on_switch_in,
+            Trigger::OnSwitchOut => &mut self.on_switch_out,
+            Trigger::BeforeMove => &mut self.before_move,
+            Trigger::AfterMoveHit => &mut self.after_move_hit,
+            Trigger::OnContact => &mut self.on_contact,
+            Trigger::OnDamaged => &mut self.on_damaged,
+            Trigger::OnStatusInflicted => &mut self.on_status_inflicted,
+            Trigger::EndOfTurn => &mut self.end_of_turn,
         }
     }
 }
@@ -0,0 +1,333 @@
+use crate::battle::{BattlePokemon, DamageContext};
+use crate::caches::names::normalize;
+use crate::hooks::{DamageHook, HookKey};
+use vdex::Ability;
+use vdex::Enum;
+use vdex::items::Item;
+use vdex::moves::{self, DamageClass, MoveId};
+use vdex::Stat;
+use vdex::Type;
+
+pub const LIGHT_CLAY_TURNS: u8 = 8;
+pub const ORAN_HEALING: u16 = 10;
+
+pub const EMBARGO_TURNS: u8 = 5;
+
+pub const KNOCK_OFF: MoveId = MoveId(281);
+pub const RECYCLE: MoveId = MoveId(277);
+pub const EMBARGO: MoveId = MoveId(372);
+// Thief and Covet.
+pub const ITEM_STEALING_MOVES: [MoveId; 2] = [MoveId(167), MoveId(342)];
+// Trick and Switcheroo.
+pub const ITEM_SWAPPING_MOVES: [MoveId; 2] = [MoveId(270), MoveId(414)];
+// Pluck and Bug Bite.
+pub const BERRY_STEALING_MOVES: [MoveId; 2] = [MoveId(364), MoveId(449)];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Berry {
+    Oran,
+    Sitrus,
+    Pinch(Stat),
+    Lum,
+    Cheri,
+    Chesto,
+    Pecha,
+    Rawst,
+    Aspear,
+    Persim,
+    Resist(Type),
+}
+
+impl Berry {
+    pub fn from_name(name: &str) -> Option<Berry> {
+        Some(match name {
+            "oranberry" => Berry::Oran,
+            "sitrusberry" => Berry::Sitrus,
+            "liechiberry" => Berry::Pinch(Stat::Attack),
+            "ganlonberry" => Berry::Pinch(Stat::Defense),
+            "salacberry" => Berry::Pinch(Stat::Speed),
+            "petayaberry" => Berry::Pinch(Stat::SpecialAttack),
+            "apicotberry" => Berry::Pinch(Stat::SpecialDefense),
+            "lumberry" => Berry::Lum,
+            "cheriberry" => Berry::Cheri,
+            "chestoberry" => Berry::Chesto,
+            "pechaberry" => Berry::Pecha,
+            "rawstberry" => Berry::Rawst,
+            "aspearberry" => Berry::Aspear,
+            "persimberry" => Berry::Persim,
+            "chilanberry" => Berry::Resist(Type::Normal),
+            "chopleberry" => Berry::Resist(Type::Fighting),
+            "cobaberry" => Berry::Resist(Type::Flying),
+            "kebiaberry" => Berry::Resist(Type::Poison),
+            "shucaberry" => Berry::Resist(Type::Ground),
+            "chartiberry" => Berry::Resist(Type::Rock),
+            "tangaberry" => Berry::Resist(Type::Bug),
+            "kasibberry" => Berry::Resist(Type::Ghost),
+            "babiriberry" => Berry::Resist(Type::Steel),
+            "occaberry" => Berry::Resist(Type::Fire),
+            "passhoberry" => Berry::Resist(Type::Water),
+            "rindoberry" => Berry::Resist(Type::Grass),
+            "wacanberry" => Berry::Resist(Type::Electric),
+            "payapaberry" => Berry::Resist(Type::Psychic),
+            "yacheberry" => Berry::Resist(Type::Ice),
+            "habanberry" => Berry::Resist(Type::Dragon),
+            "colburberry" => Berry::Resist(Type::Dark),
+            _ => return None,
+        })
+    }
+
+    pub fn triggers(self, pokemon: &BattlePokemon) -> bool {
+        let hp = pokemon.perm.borrow().hp;
+        let max_hp = pokemon.overlay.stat(Stat::HP);
+        match self {
+            Berry::Oran | Berry::Sitrus => hp * 2 <= max_hp,
+            Berry::Pinch(_) if pokemon.overlay.ability == Ability::Gluttony
+                => hp * 2 <= max_hp,
+            Berry::Pinch(_) => hp * 4 <= max_hp,
+            Berry::Lum => !pokemon.perm.borrow().status.is_none()
+                || pokemon.is_confused(),
+            Berry::Cheri => pokemon.is_paralyzed(),
+            Berry::Chesto => pokemon.is_asleep(),
+            Berry::Pecha => pokemon.is_poisoned(),
+            Berry::Rawst => pokemon.is_burned(),
+            Berry::Aspear => pokemon.is_frozen(),
+            Berry::Persim => pokemon.is_confused(),
+            Berry::Resist(_) => false,
+        }
+    }
+
+    pub fn eat(self, pokemon: &mut BattlePokemon) -> bool {
+        let max_hp = pokemon.overlay.stat(Stat::HP);
+        match self {
+            Berry::Oran => pokemon.direct_heal(ORAN_HEALING) > 0,
+            Berry::Sitrus => pokemon.direct_heal((max_hp / 4).max(1)) > 0,
+            Berry::Pinch(stat) => {
+                let mut changes = [0; moves::CHANGEABLE_STATS];
+                changes[stat.repr() as usize] = 1;
+                pokemon.change_stats(changes) != [0; moves::CHANGEABLE_STATS]
+            },
+            Berry::Lum => {
+                let cured = pokemon.cure();
+                pokemon.cure_confusion() || cured
+            },
+            Berry::Persim => pokemon.cure_confusion(),
+            Berry::Resist(_) => false,
+            _ => self.triggers(pokemon) && pokemon.cure(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum HeldItem {
+    ChoiceBand,
+    ChoiceScarf,
+    ChoiceSpecs,
+    LifeOrb,
+    Leftovers,
+    BlackSludge,
+    TypeBoost(Type),
+    ExpertBelt,
+    MuscleBand,
+    WiseGlasses,
+    ScopeLens,
+    RazorClaw,
+    BrightPowder,
+    LaxIncense,
+    FocusSash,
+    LightClay,
+    Berry(Berry),
+}
+
+impl HeldItem {
+    pub fn from_item(item: &Item) -> Option<HeldItem> {
+        // Items are matched by name, as their ids depend on the data set.
+        let name = normalize(&item.name);
+        if let Some(berry) = Berry::from_name(&name) {
+            return Some(HeldItem::Berry(berry));
+        }
+        Some(match name.as_str() {
+            "choiceband" => HeldItem::ChoiceBand,
+            "choicescarf" => HeldItem::ChoiceScarf,
+            "choicespecs" => HeldItem::ChoiceSpecs,
+            "lifeorb" => HeldItem::LifeOrb,
+            "leftovers" => HeldItem::Leftovers,
+            "blacksludge" => HeldItem::BlackSludge,
+            "expertbelt" => HeldItem::ExpertBelt,
+            "muscleband" => HeldItem::MuscleBand,
+            "wiseglasses" => HeldItem::WiseGlasses,
+            "scopelens" => HeldItem::ScopeLens,
+            "razorclaw" => HeldItem::RazorClaw,
+            "brightpowder" => HeldItem::BrightPowder,
+            "laxincense" => HeldItem::LaxIncense,
+            "focussash" => HeldItem::FocusSash,
+            "lightclay" => HeldItem::LightClay,
+            "silkscarf" => HeldItem::TypeBoost(Type::Normal),
+            "blackbelt" | "fistplate" => HeldItem::TypeBoost(Type::Fighting),
+            "sharpbeak" | "skyplate" => HeldItem::TypeBoost(Type::Flying),
+            "poisonbarb" | "toxicplate" => HeldItem::TypeBoost(Type::Poison),
+            "softsand" | "earthplate" => HeldItem::TypeBoost(Type::Ground),
+            "hardstone" | "stoneplate" | "rockincense"
+                => HeldItem::TypeBoost(Type::Rock),
+            "silverpowder" | "insectplate" => HeldItem::TypeBoost(Type::Bug),
+            "spelltag" | "spookyplate" => HeldItem::TypeBoost(Type::Ghost),
+            "metalcoat" | "ironplate" => HeldItem::TypeBoost(Type::Steel),
+            "charcoal" | "flameplate" => HeldItem::TypeBoost(Type::Fire),
+            "mysticwater" | "splashplate" | "seaincense" | "waveincense"
+                => HeldItem::TypeBoost(Type::Water),
+            "miracleseed" | "meadowplate" | "roseincense"
+                => HeldItem::TypeBoost(Type::Grass),
+            "magnet" | "zapplate" => HeldItem::TypeBoost(Type::Electric),
+            "twistedspoon" | "mindplate" | "oddincense"
+                => HeldItem::TypeBoost(Type::Psychic),
+            "nevermeltice" | "icicleplate" => HeldItem::TypeBoost(Type::Ice),
+            "dragonfang" | "dracoplate" => HeldItem::TypeBoost(Type::Dragon),
+            "blackglasses" | "dreadplate" => HeldItem::TypeBoost(Type::Dark),
+            _ => return None,
+        })
+    }
+
+    pub fn is_choice(self) -> bool {
+        match self {
+            HeldItem::ChoiceBand | HeldItem::ChoiceScarf
+                | HeldItem::ChoiceSpecs => true,
+            _ => false,
+        }
+    }
+
+    pub fn critical_stages(self) -> i8 {
+        match self {
+            HeldItem::ScopeLens | HeldItem::RazorClaw => 1,
+            _ => 0,
+        }
+    }
+
+    pub fn power_modifier(self) -> Option<DamageHook> {
+        match self {
+            HeldItem::TypeBoost(_) => Some(DamageHook(|ctx, user, _| {
+                match user.held_item() {
+                    Some(HeldItem::TypeBoost(typ)) if Some(typ) == ctx.typ
+                        => 1.2,
+                    _ => 1.0,
+                }
+            })),
+            HeldItem::MuscleBand => Some(DamageHook(|ctx, _, _| {
+                match ctx.class {
+                    DamageClass::Physical => 1.1,
+                    _ => 1.0,
+                }
+            })),
+            HeldItem::WiseGlasses => Some(DamageHook(|ctx, _, _| {
+                match ctx.class {
+                    DamageClass::Special => 1.1,
+                    _ => 1.0,
+                }
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn attack_modifier(self) -> Option<DamageHook> {
+        match self {
+            HeldItem::ChoiceBand => Some(DamageHook(|ctx, _, _| {
+                match (ctx.typ, ctx.class) {
+                    (Some(_), DamageClass::Physical) => 1.5,
+                    _ => 1.0,
+                }
+            })),
+            HeldItem::ChoiceSpecs => Some(DamageHook(|ctx, _, _| {
+                match (ctx.typ, ctx.class) {
+                    (Some(_), DamageClass::Special) => 1.5,
+                    _ => 1.0,
+                }
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn user_damage_modifier(self) -> Option<DamageHook> {
+        match self {
+            HeldItem::LifeOrb => Some(DamageHook(|ctx, _, _| {
+                if ctx.typ.is_some() { 1.3 } else { 1.0 }
+            })),
+            HeldItem::ExpertBelt => Some(DamageHook(|ctx, _, target| {
+                if ctx.effectiveness_against(target) > 1.0 { 1.2 } else { 1.0 }
+            })),
+            _ => None,
+        }
+    }
+
+    pub fn target_damage_modifier(self) -> Option<DamageHook> {
+        match self {
+            HeldItem::Berry(Berry::Resist(_))
+                => Some(DamageHook(|ctx, _, target| {
+                    if resist_berry(ctx, target) { 0.5 } else { 1.0 }
+                })),
+            _ => None,
+        }
+    }
+
+    pub fn target_accuracy_modifier(self) -> Option<DamageHook> {
+        match self {
+            HeldItem::BrightPowder | HeldItem::LaxIncense
+                => Some(DamageHook(|_, _, _| 0.9)),
+            _ => None,
+        }
+    }
+}
+
+pub fn resist_berry(ctx: &DamageContext, target: &BattlePokemon) -> bool {
+    if ctx.mov.power == 0 {
+        return false;
+    }
+    match target.held_item() {
+        Some(HeldItem::Berry(Berry::Resist(typ))) => Some(typ) == ctx.typ
+            && (typ == Type::Normal
+                || ctx.effectiveness_against(target) > 1.0),
+        _ => false,
+    }
+}
+
+pub fn check_berry(pokemon: &mut BattlePokemon) {
+    if let Some(HeldItem::Berry(berry)) = pokemon.held_item() {
+        if !pokemon.is_fainted() && berry.triggers(pokemon) {
+            pokemon.consume_item();
+            berry.eat(pokemon);
+        }
+    }
+}
+
+pub fn life_orb(pokemon: &mut BattlePokemon) {
+    if let Some(HeldItem::LifeOrb) = pokemon.held_item() {
+        if pokemon.overlay.ability != Ability::MagicGuard {
+            let max_hp = pokemon.overlay.stat(Stat::HP);
+            pokemon.direct_damage((max_hp / 10).max(1));
+        }
+    }
+}
+
+pub fn install(pokemon: &mut BattlePokemon) {
+    // Embargoed Pokémon report no held item, so nothing is installed for them.
+    let held = match pokemon.held_item() {
+        Some(held) => held,
+        None => return,
+    };
+    let item = pokemon.perm.borrow().held.unwrap();
+    let key = HookKey::new_item(0, item.id, 0);
+    let hooks = &mut pokemon.hooks;
+    if let Some(hook) = held.power_modifier() {
+        hooks.power_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = held.attack_modifier() {
+        hooks.attack_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = held.user_damage_modifier() {
+        hooks.user_damage_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = held.target_damage_modifier() {
+        hooks.target_damage_modifiers.overlay.insert(key, hook);
+    }
+    if let Some(hook) = held.target_accuracy_modifier() {
+        hooks.target_accuracy_modifiers.overlay.insert(key, hook);
+    }
+    pokemon.critical_rate += held.critical_stages();
+}
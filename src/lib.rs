@@ -2,11 +2,23 @@
 extern crate bitflags;
 extern crate enum_repr;
 
+pub mod abilities;
 pub mod ailments;
 pub mod battle;
+pub mod client;
+pub mod events;
 pub mod exec;
+pub mod field;
 pub mod formats;
 pub mod hooks;
+pub mod items;
+pub mod preview;
+pub mod protocol;
+pub mod replay;
+pub mod rules;
+pub mod server;
+pub mod showdown;
+pub mod shared;
 pub mod caches;
 pub mod team;
 
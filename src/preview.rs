@@ -0,0 +1,84 @@
+use crate::formats::SingleBattle;
+use crate::rules::Ruleset;
+use crate::team::Team;
+use vdex::pokemon::PokemonId;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PreviewError {
+    InvalidIndex,
+    DuplicateIndex,
+    WrongCount { expected: usize },
+    NotReady,
+    InvalidTeam,
+}
+
+pub struct TeamPreview {
+    pub teams: [Team; 2],
+    pub rules: Ruleset,
+    pub orders: [Option<Vec<usize>>; 2],
+}
+
+impl TeamPreview {
+    pub fn new(team1: &Team, team2: &Team, rules: Ruleset) -> Self {
+        Self {
+            teams: [team1.clone(), team2.clone()],
+            rules,
+            orders: [None, None],
+        }
+    }
+
+    pub fn picks(&self, side: usize) -> usize {
+        let size = self.teams[side].len();
+        self.rules.picks.map_or(size, |picks| picks.min(size))
+    }
+
+    pub fn revealed(&self, side: usize) -> Vec<PokemonId> {
+        // Only species are revealed; moves, items and spreads stay hidden.
+        self.teams[side].iter().map(|member| member.pokemon.id).collect()
+    }
+
+    pub fn check_order(
+        &self, side: usize, order: &[usize]
+    ) -> Result<(), PreviewError> {
+        let expected = self.picks(side);
+        if order.len() != expected {
+            return Err(PreviewError::WrongCount { expected });
+        }
+        for (i, index) in order.iter().enumerate() {
+            if *index >= self.teams[side].len() {
+                return Err(PreviewError::InvalidIndex);
+            } else if order[..i].contains(index) {
+                return Err(PreviewError::DuplicateIndex);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn choose(
+        &mut self, side: usize, order: Vec<usize>
+    ) -> Result<(), PreviewError> {
+        self.check_order(side, &order)?;
+        self.orders[side] = Some(order);
+        Ok(())
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.orders.iter().all(|order| order.is_some())
+    }
+
+    fn chosen(&self, side: usize) -> Result<Team, PreviewError> {
+        let order = self.orders[side].as_ref().ok_or(PreviewError::NotReady)?;
+        Ok(order.iter().map(|index| self.teams[side][*index].clone()).collect())
+    }
+
+    pub fn start(self) -> Result<SingleBattle, PreviewError> {
+        self.start_seeded(rand::random())
+    }
+
+    pub fn start_seeded(self, seed: u64) -> Result<SingleBattle, PreviewError> {
+        let team1 = self.chosen(0)?;
+        let team2 = self.chosen(1)?;
+        SingleBattle::new_validated(&team1, &team2, self.rules, seed)
+            .map_err(|_| PreviewError::InvalidTeam)
+    }
+}
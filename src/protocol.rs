@@ -0,0 +1,400 @@
+use crate::ailments::BenchAilment;
+use crate::events::Event;
+use crate::exec::premove::MoveBlock;
+use crate::exec::residual;
+use crate::exec::turn::{Action, BattleOutcome};
+use crate::field::{
+    HAZARDS, Hazard, SIDE_CONDITIONS, SideCondition, WEATHERS, Weather};
+use crate::formats::AbsoluteTarget;
+use crate::team::{Team, TeamMember};
+use std::sync::Arc;
+use vdex::Enum;
+use vdex::items::ItemId;
+use vdex::moves::MoveId;
+use vdex::pokemon::PokemonId;
+use vdex::Stat;
+
+// The wire protocol is line-based: every message is a single line of
+// space-separated tokens, with the message kind first.
+
+pub const MOVE_BLOCKS: [MoveBlock; 6] = [
+    MoveBlock::Asleep,
+    MoveBlock::Frozen,
+    MoveBlock::Flinched,
+    MoveBlock::Confused,
+    MoveBlock::Infatuated,
+    MoveBlock::FullyParalyzed,
+];
+
+#[derive(Clone, Debug)]
+pub enum ClientMessage {
+    Team(Team),
+    Action(Action),
+    Replace(usize),
+    Order(Vec<usize>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ServerMessage {
+    Side(usize),
+    Preview { species: Vec<PokemonId>, picks: usize },
+    TurnRequest {
+        moves: [Option<(MoveId, u8)>; 4],
+        switches: Vec<usize>,
+    },
+    ReplaceRequest { switches: Vec<usize> },
+    Event(Event),
+    Error(String),
+    End(BattleOutcome),
+}
+
+fn encode_list(list: &[usize]) -> String {
+    if list.is_empty() {
+        "-".to_string()
+    } else {
+        list.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(",")
+    }
+}
+
+fn decode_list(token: &str) -> Option<Vec<usize>> {
+    if token == "-" {
+        Some(Vec::new())
+    } else {
+        token.split(',').map(|i| i.parse().ok()).collect()
+    }
+}
+
+fn encode_outcome(outcome: BattleOutcome) -> &'static str {
+    match outcome {
+        BattleOutcome::Battler1Wins => "1",
+        BattleOutcome::Battler2Wins => "2",
+        BattleOutcome::Draw => "draw",
+    }
+}
+
+fn decode_outcome(token: &str) -> Option<BattleOutcome> {
+    match token {
+        "1" => Some(BattleOutcome::Battler1Wins),
+        "2" => Some(BattleOutcome::Battler2Wins),
+        "draw" => Some(BattleOutcome::Draw),
+        _ => None,
+    }
+}
+
+fn encode_status(status: BenchAilment) -> String {
+    match status {
+        BenchAilment::None => "none".to_string(),
+        BenchAilment::Paralyzed => "par".to_string(),
+        BenchAilment::Asleep { remaining_turns }
+            => format!("slp:{}", remaining_turns),
+        BenchAilment::Frozen => "frz".to_string(),
+        BenchAilment::Burned => "brn".to_string(),
+        BenchAilment::Poisoned { bad: false } => "psn".to_string(),
+        BenchAilment::Poisoned { bad: true } => "tox".to_string(),
+    }
+}
+
+fn decode_status(token: &str) -> Option<BenchAilment> {
+    match token {
+        "none" => Some(BenchAilment::None),
+        "par" => Some(BenchAilment::Paralyzed),
+        "frz" => Some(BenchAilment::Frozen),
+        "brn" => Some(BenchAilment::Burned),
+        "psn" => Some(BenchAilment::Poisoned { bad: false }),
+        "tox" => Some(BenchAilment::Poisoned { bad: true }),
+        _ if token.starts_with("slp:") => Some(BenchAilment::Asleep {
+            remaining_turns: token[4..].parse().ok()?,
+        }),
+        _ => None,
+    }
+}
+
+pub fn encode_action(action: Action) -> String {
+    match action {
+        Action::Move(slot) => format!("move {}", slot),
+        Action::Switch(index) => format!("switch {}", index),
+        Action::Forfeit => "forfeit".to_string(),
+    }
+}
+
+pub fn encode_event(event: &Event) -> String {
+    let t = |target: &AbsoluteTarget| target.repr();
+    match event {
+        Event::Turn(turn) => format!("turn {}", turn),
+        Event::MoveUsed { user, mov } => format!("move_used {} {}", t(user), mov.0),
+        Event::MoveBlocked { user, reason }
+            => format!("move_blocked {} {:?}", t(user), reason),
+        Event::MoveFailed { user } => format!("move_failed {}", t(user)),
+        Event::Miss { user, target }
+            => format!("miss {} {}", t(user), t(target)),
+        Event::Damage { target, amount, critical, effectiveness }
+            => format!("damage {} {} {} {}", t(target), amount,
+                *critical as u8, effectiveness),
+        Event::Heal { target, amount } => format!("heal {} {}", t(target), amount),
+        Event::StatChange { target, stat, change }
+            => format!("stat_change {} {} {}", t(target), stat.repr(), change),
+        Event::StatChangeFailed { target, stat, change }
+            => format!("stat_change_failed {} {} {}",
+                t(target), stat.repr(), change),
+        Event::StatusInflicted { target, status }
+            => format!("status {} {}", t(target), encode_status(*status)),
+        Event::StatusCured { target } => format!("cured {}", t(target)),
+        Event::Confused { target } => format!("confused {}", t(target)),
+        Event::ConfusionEnded { target }
+            => format!("confusion_ended {}", t(target)),
+        Event::Residual { target, kind }
+            => format!("residual {} {:?}", t(target), kind),
+        Event::PerishCount { target, count }
+            => format!("perish {} {}", t(target), count),
+        Event::WeatherStarted { weather } => format!("weather {:?}", weather),
+        Event::WeatherEnded { weather }
+            => format!("weather_ended {:?}", weather),
+        Event::SideConditionStarted { side, condition }
+            => format!("side_condition {} {:?}", side, condition),
+        Event::SideConditionEnded { side, condition }
+            => format!("side_condition_ended {} {:?}", side, condition),
+        Event::HazardSet { side, hazard, layers }
+            => format!("hazard_set {} {:?} {}", side, hazard, layers),
+        Event::HazardRemoved { side, hazard }
+            => format!("hazard_removed {} {:?}", side, hazard),
+        Event::Hazard { target, hazard }
+            => format!("hazard {} {:?}", t(target), hazard),
+        Event::ItemConsumed { target, item }
+            => format!("item_consumed {} {}", t(target), item.0),
+        Event::ItemRemoved { target, item }
+            => format!("item_removed {} {}", t(target), item.0),
+        Event::ItemGained { target, item }
+            => format!("item_gained {} {}", t(target), item.0),
+        Event::Embargoed { target } => format!("embargo {}", t(target)),
+        Event::EmbargoEnded { target }
+            => format!("embargo_ended {}", t(target)),
+        Event::Faint { target } => format!("faint {}", t(target)),
+        Event::Switch { target, index }
+            => format!("switch {} {}", t(target), index),
+        Event::Forfeit { side } => format!("forfeit {}", side),
+        Event::End(outcome) => format!("end {}", encode_outcome(*outcome)),
+    }
+}
+
+pub fn decode_event(tokens: &[&str]) -> Option<Event> {
+    let target = |i: usize| -> Option<AbsoluteTarget> {
+        AbsoluteTarget::from_repr(tokens.get(i)?.parse().ok()?)
+    };
+    let number = |i: usize| -> Option<u16> { tokens.get(i)?.parse().ok() };
+    let change = |i: usize| -> Option<i8> { tokens.get(i)?.parse().ok() };
+    let stat = |i: usize| -> Option<Stat> {
+        Stat::from_repr(tokens.get(i)?.parse().ok()?)
+    };
+    let name = |i: usize| tokens.get(i).cloned();
+    let item = |i: usize| -> Option<ItemId> {
+        Some(ItemId(tokens.get(i)?.parse().ok()?))
+    };
+    let weather = |i: usize| -> Option<Weather> {
+        let name = tokens.get(i)?;
+        WEATHERS.iter().find(|weather| format!("{:?}", weather) == *name)
+            .cloned()
+    };
+    let hazard = |i: usize| -> Option<Hazard> {
+        let name = tokens.get(i)?;
+        HAZARDS.iter().find(|hazard| format!("{:?}", hazard) == *name).cloned()
+    };
+    let condition = |i: usize| -> Option<SideCondition> {
+        let name = tokens.get(i)?;
+        SIDE_CONDITIONS.iter()
+            .find(|condition| format!("{:?}", condition) == *name).cloned()
+    };
+    Some(match *tokens.first()? {
+        "turn" => Event::Turn(number(1)?),
+        "move_used" => Event::MoveUsed {
+            user: target(1)?,
+            mov: MoveId(tokens.get(2)?.parse().ok()?),
+        },
+        "move_blocked" => {
+            let reason = name(2)?;
+            Event::MoveBlocked {
+                user: target(1)?,
+                reason: *MOVE_BLOCKS.iter()
+                    .find(|block| format!("{:?}", block) == reason)?,
+            }
+        },
+        "move_failed" => Event::MoveFailed { user: target(1)? },
+        "miss" => Event::Miss { user: target(1)?, target: target(2)? },
+        "damage" => Event::Damage {
+            target: target(1)?,
+            amount: number(2)?,
+            critical: name(3)? == "1",
+            effectiveness: name(4)?.parse().ok()?,
+        },
+        "heal" => Event::Heal { target: target(1)?, amount: number(2)? },
+        "stat_change" => Event::StatChange {
+            target: target(1)?,
+            stat: stat(2)?,
+            change: change(3)?,
+        },
+        "stat_change_failed" => Event::StatChangeFailed {
+            target: target(1)?,
+            stat: stat(2)?,
+            change: change(3)?,
+        },
+        "status" => Event::StatusInflicted {
+            target: target(1)?,
+            status: decode_status(name(2)?)?,
+        },
+        "cured" => Event::StatusCured { target: target(1)? },
+        "confused" => Event::Confused { target: target(1)? },
+        "confusion_ended" => Event::ConfusionEnded { target: target(1)? },
+        "residual" => {
+            let kind = name(2)?;
+            Event::Residual {
+                target: target(1)?,
+                kind: *residual::ORDER.iter()
+                    .find(|residual| format!("{:?}", residual) == kind)?,
+            }
+        },
+        "perish" => Event::PerishCount {
+            target: target(1)?,
+            count: tokens.get(2)?.parse().ok()?,
+        },
+        "weather" => Event::WeatherStarted { weather: weather(1)? },
+        "weather_ended" => Event::WeatherEnded { weather: weather(1)? },
+        "side_condition" => Event::SideConditionStarted {
+            side: tokens.get(1)?.parse().ok()?,
+            condition: condition(2)?,
+        },
+        "side_condition_ended" => Event::SideConditionEnded {
+            side: tokens.get(1)?.parse().ok()?,
+            condition: condition(2)?,
+        },
+        "hazard_set" => Event::HazardSet {
+            side: tokens.get(1)?.parse().ok()?,
+            hazard: hazard(2)?,
+            layers: tokens.get(3)?.parse().ok()?,
+        },
+        "hazard_removed" => Event::HazardRemoved {
+            side: tokens.get(1)?.parse().ok()?,
+            hazard: hazard(2)?,
+        },
+        "hazard" => Event::Hazard { target: target(1)?, hazard: hazard(2)? },
+        "item_consumed" => Event::ItemConsumed {
+            target: target(1)?,
+            item: item(2)?,
+        },
+        "item_removed" => Event::ItemRemoved {
+            target: target(1)?,
+            item: item(2)?,
+        },
+        "item_gained" => Event::ItemGained {
+            target: target(1)?,
+            item: item(2)?,
+        },
+        "embargo" => Event::Embargoed { target: target(1)? },
+        "embargo_ended" => Event::EmbargoEnded { target: target(1)? },
+        "faint" => Event::Faint { target: target(1)? },
+        "switch" => Event::Switch {
+            target: target(1)?,
+            index: tokens.get(2)?.parse().ok()?,
+        },
+        "forfeit" => Event::Forfeit { side: tokens.get(1)?.parse().ok()? },
+        "end" => Event::End(decode_outcome(name(1)?)?),
+        _ => return None,
+    })
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> String {
+        match self {
+            ClientMessage::Team(team) => format!("team {}", team.iter()
+                .map(|member| member.encode()).collect::<Vec<_>>().join("|")),
+            ClientMessage::Action(action) => encode_action(*action),
+            ClientMessage::Replace(index) => format!("replace {}", index),
+            ClientMessage::Order(order)
+                => format!("order {}", encode_list(order)),
+        }
+    }
+
+    pub fn decode(line: &str) -> Option<ClientMessage> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let index = |i: usize| -> Option<usize> { tokens.get(i)?.parse().ok() };
+        Some(match *tokens.first()? {
+            "team" => {
+                let mut team = Team::new();
+                for member in line.trim_start()["team".len()..].split('|') {
+                    team.push(Arc::new(TeamMember::decode(member)?));
+                }
+                ClientMessage::Team(team)
+            },
+            "move" => ClientMessage::Action(
+                Action::Move(tokens.get(1)?.parse().ok()?)),
+            "switch" => ClientMessage::Action(Action::Switch(index(1)?)),
+            "forfeit" => ClientMessage::Action(Action::Forfeit),
+            "replace" => ClientMessage::Replace(index(1)?),
+            "order" => ClientMessage::Order(decode_list(tokens.get(1)?)?),
+            _ => return None,
+        })
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> String {
+        match self {
+            ServerMessage::Side(side) => format!("side {}", side),
+            ServerMessage::Preview { species, picks } => {
+                let species = species.iter().map(|id| id.0 as usize)
+                    .collect::<Vec<_>>();
+                format!("preview {} {}", picks, encode_list(&species))
+            },
+            ServerMessage::TurnRequest { moves, switches } => {
+                let moves = moves.iter().map(|slot| slot.map_or(
+                    "-".to_string(), |(mov, pp)| format!("{}:{}", mov.0, pp)))
+                    .collect::<Vec<_>>().join(" ");
+                format!("request turn {} {}", moves, encode_list(switches))
+            },
+            ServerMessage::ReplaceRequest { switches }
+                => format!("request replace {}", encode_list(switches)),
+            ServerMessage::Event(event)
+                => format!("event {}", encode_event(event)),
+            ServerMessage::Error(message) => format!("error {}", message),
+            ServerMessage::End(outcome)
+                => format!("end {}", encode_outcome(*outcome)),
+        }
+    }
+
+    pub fn decode(line: &str) -> Option<ServerMessage> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        Some(match *tokens.first()? {
+            "side" => ServerMessage::Side(tokens.get(1)?.parse().ok()?),
+            "preview" => ServerMessage::Preview {
+                picks: tokens.get(1)?.parse().ok()?,
+                species: decode_list(tokens.get(2)?)?.into_iter()
+                    .map(|id| PokemonId(id as _)).collect(),
+            },
+            "request" => match *tokens.get(1)? {
+                "turn" => {
+                    let mut moves = [None; 4];
+                    for (i, slot) in moves.iter_mut().enumerate() {
+                        let token = tokens.get(2 + i)?;
+                        if *token != "-" {
+                            let mut split = token.split(':');
+                            let mov = MoveId(split.next()?.parse().ok()?);
+                            let pp = split.next()?.parse().ok()?;
+                            *slot = Some((mov, pp));
+                        }
+                    }
+                    ServerMessage::TurnRequest {
+                        moves,
+                        switches: decode_list(tokens.get(6)?)?,
+                    }
+                },
+                "replace" => ServerMessage::ReplaceRequest {
+                    switches: decode_list(tokens.get(2)?)?,
+                },
+                _ => return None,
+            },
+            "event" => ServerMessage::Event(decode_event(&tokens[1..])?),
+            "error" => ServerMessage::Error(
+                line.trim_start()["error".len()..].trim().to_string()),
+            "end" => ServerMessage::End(decode_outcome(tokens.get(1)?)?),
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,151 @@
+use crate::exec::turn::{Action, ActionError, Choice};
+use crate::formats::SingleBattle;
+use crate::rules::{Clauses, Ruleset};
+use crate::team::{Team, TeamMember};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+pub const HEADER: &str = "pbirch-replay 2";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Parse(usize),
+    Action(ActionError),
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub clauses: Clauses,
+    pub team1: Team,
+    pub team2: Team,
+    pub choices: Vec<Choice>,
+}
+
+fn encode_action(action: Action) -> String {
+    match action {
+        Action::Move(slot) => format!("m{}", slot),
+        Action::Switch(index) => format!("s{}", index),
+        Action::Forfeit => "f".to_string(),
+    }
+}
+
+fn decode_action(token: &str) -> Option<Action> {
+    match token.chars().next()? {
+        'm' => Some(Action::Move(token[1..].parse().ok()?)),
+        's' => Some(Action::Switch(token[1..].parse().ok()?)),
+        'f' if token.len() == 1 => Some(Action::Forfeit),
+        _ => None,
+    }
+}
+
+impl Replay {
+    pub fn encode(&self) -> String {
+        let mut lines = vec![
+            HEADER.to_string(),
+            format!("seed {}", self.seed),
+            format!("clauses {}", self.clauses.bits()),
+        ];
+        for (name, team) in [("team1", &self.team1), ("team2", &self.team2)].iter() {
+            lines.push(format!("{} {}", name, team.len()));
+            for member in team.iter() {
+                lines.push(member.encode());
+            }
+        }
+        for choice in self.choices.iter() {
+            lines.push(match choice {
+                Choice::Turn(action1, action2) => format!("turn {} {}",
+                    encode_action(*action1), encode_action(*action2)),
+                Choice::Replace(side, index)
+                    => format!("replace {} {}", side, index),
+                Choice::Forfeit(side) => format!("forfeit {}", side),
+            });
+        }
+        lines.join("\n") + "\n"
+    }
+
+    pub fn decode(text: &str) -> Result<Replay, ReplayError> {
+        let mut lines = text.lines().enumerate();
+        let mut next = || lines.next().ok_or(ReplayError::Parse(0));
+        if next()?.1 != HEADER {
+            return Err(ReplayError::Parse(1));
+        }
+        let (number, line) = next()?;
+        let seed = line.trim_start_matches("seed ").parse()
+            .map_err(|_| ReplayError::Parse(number + 1))?;
+        let (number, line) = next()?;
+        let clauses = line.trim_start_matches("clauses ").parse().ok()
+            .and_then(Clauses::from_bits)
+            .ok_or(ReplayError::Parse(number + 1))?;
+        let mut teams = Vec::new();
+        for name in ["team1 ", "team2 "].iter() {
+            let (number, line) = next()?;
+            let size: usize = line.trim_start_matches(name).parse()
+                .map_err(|_| ReplayError::Parse(number + 1))?;
+            let mut team = Team::new();
+            for _ in 0..size {
+                let (number, line) = next()?;
+                team.push(Arc::new(TeamMember::decode(line)
+                    .ok_or(ReplayError::Parse(number + 1))?));
+            }
+            teams.push(team);
+        }
+        let mut choices = Vec::new();
+        for (number, line) in lines {
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            let choice = match tokens.as_slice() {
+                ["turn", action1, action2] => decode_action(action1)
+                    .and_then(|a1| decode_action(action2)
+                        .map(|a2| Choice::Turn(a1, a2))),
+                ["replace", side, index] => side.parse().ok()
+                    .and_then(|side| index.parse().ok()
+                        .map(|index| Choice::Replace(side, index))),
+                ["forfeit", side] => side.parse().ok().map(Choice::Forfeit),
+                [] => continue,
+                _ => None,
+            };
+            choices.push(choice.ok_or(ReplayError::Parse(number + 1))?);
+        }
+        let team2 = teams.pop().unwrap();
+        let team1 = teams.pop().unwrap();
+        Ok(Replay { seed, clauses, team1, team2, choices })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Replay, ReplayError> {
+        Replay::decode(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        Ok(fs::write(path, self.encode())?)
+    }
+
+    pub fn play(&self) -> Result<SingleBattle, ReplayError> {
+        let rules = Ruleset { clauses: self.clauses, ..Default::default() };
+        let mut battle = SingleBattle::new_seeded(
+            &self.team1, &self.team2, rules, self.seed);
+        for choice in self.choices.iter() {
+            match *choice {
+                Choice::Turn(action1, action2) => {
+                    battle.run_turn(action1, action2)
+                        .map_err(ReplayError::Action)?;
+                },
+                Choice::Replace(side, index) => {
+                    battle.replace(side, index).map_err(ReplayError::Action)?;
+                },
+                Choice::Forfeit(side) => {
+                    battle.forfeit(side);
+                },
+            }
+        }
+        Ok(battle)
+    }
+}
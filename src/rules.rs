@@ -0,0 +1,193 @@
+use crate::battle::BattlePokemon;
+use crate::ailments::BenchAilment;
+use crate::caches::pokemon::species_ref;
+use crate::hooks::{self, HookKey, Hooks, StatusHook};
+use crate::team::{self, Team, TeamViolation};
+use std::sync::Arc;
+use vdex::Ability;
+use vdex::items::ItemId;
+use vdex::moves::{Effect, MoveId};
+use vdex::pokemon::PokemonId;
+
+pub const EVASION_MOVES: [MoveId; 2] = [MoveId(103), MoveId(106)];
+
+bitflags! {
+    #[derive(Default)]
+    pub struct Clauses: u8 {
+        const SPECIES = 0x01;
+        const ITEM    = 0x02;
+        const SLEEP   = 0x04;
+        const EVASION = 0x08;
+        const OHKO    = 0x10;
+        const SELF_KO = 0x20;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RuleViolation {
+    Team(TeamViolation),
+    TeamTooSmall { size: usize },
+    TeamTooLarge { size: usize },
+    LevelAboveCap { index: usize, level: u8 },
+    BannedPokemon { index: usize, pokemon: PokemonId },
+    BannedMove { index: usize, mov: MoveId },
+    BannedItem { index: usize, item: ItemId },
+    BannedAbility { index: usize, ability: Ability },
+    SpeciesClause { index: usize },
+    ItemClause { index: usize },
+    EvasionClause { index: usize, mov: MoveId },
+    OhkoClause { index: usize, mov: MoveId },
+}
+
+#[derive(Clone, Debug)]
+pub struct Ruleset {
+    pub min_team_size: usize,
+    pub max_team_size: usize,
+    pub level_cap: u8,
+    pub level_scale: Option<u8>,
+    pub banned_pokemon: Vec<PokemonId>,
+    pub banned_moves: Vec<MoveId>,
+    pub banned_items: Vec<ItemId>,
+    pub banned_abilities: Vec<Ability>,
+    pub clauses: Clauses,
+    pub team_preview: bool,
+    pub picks: Option<usize>,
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Self {
+            min_team_size: 1,
+            max_team_size: team::MAX_TEAM_SIZE,
+            level_cap: team::MAX_LEVEL,
+            level_scale: None,
+            banned_pokemon: Vec::new(),
+            banned_moves: Vec::new(),
+            banned_items: Vec::new(),
+            banned_abilities: Vec::new(),
+            clauses: Clauses::empty(),
+            team_preview: false,
+            picks: None,
+        }
+    }
+}
+
+fn sleep_clause(pokemon: &BattlePokemon, status: &BenchAilment) -> bool {
+    // Rest is not implemented yet, so every sleep counts as foe-inflicted.
+    match status {
+        BenchAilment::Asleep { .. } => pokemon.team.iter().enumerate()
+            .any(|(index, ally)| index != pokemon.index && {
+                let ally = ally.borrow();
+                ally.hp > 0 && match ally.status {
+                    BenchAilment::Asleep { .. } => true,
+                    _ => false,
+                }
+            }),
+        _ => false,
+    }
+}
+
+impl Ruleset {
+    pub fn standard() -> Self {
+        Self {
+            clauses: Clauses::SPECIES | Clauses::SLEEP | Clauses::EVASION
+                | Clauses::OHKO | Clauses::SELF_KO,
+            ..Default::default()
+        }
+    }
+
+    pub fn validate(
+        &self, team: &Team, skip_moveset: bool
+    ) -> Vec<RuleViolation> {
+        // The ruleset has its own size limits, so only keep the violations
+        // of individual members.
+        let mut violations = team::validate_team(team, skip_moveset)
+            .into_iter().filter(|violation| {
+                if let TeamViolation::Member { .. } = violation {
+                    true
+                } else {
+                    false
+                }
+            }).map(RuleViolation::Team).collect::<Vec<_>>();
+        if team.len() < self.min_team_size {
+            violations.push(RuleViolation::TeamTooSmall { size: team.len() });
+        } else if team.len() > self.max_team_size {
+            violations.push(RuleViolation::TeamTooLarge { size: team.len() });
+        }
+        for (index, member) in team.iter().enumerate() {
+            if member.level > self.level_cap && self.level_scale.is_none() {
+                violations.push(RuleViolation::LevelAboveCap {
+                    index,
+                    level: member.level,
+                });
+            }
+            if self.banned_pokemon.contains(&member.pokemon.id) {
+                violations.push(RuleViolation::BannedPokemon {
+                    index,
+                    pokemon: member.pokemon.id,
+                });
+            }
+            if self.banned_abilities.contains(&member.ability) {
+                violations.push(RuleViolation::BannedAbility {
+                    index,
+                    ability: member.ability,
+                });
+            }
+            if let Some(item) = member.held {
+                if self.banned_items.contains(&item.id) {
+                    violations.push(
+                        RuleViolation::BannedItem { index, item: item.id });
+                }
+                if self.clauses.contains(Clauses::ITEM) && team[..index].iter()
+                    .any(|other| other.held.map_or(false, |o| o.id == item.id))
+                {
+                    violations.push(RuleViolation::ItemClause { index });
+                }
+            }
+            let species = species_ref(member.pokemon.id);
+            if self.clauses.contains(Clauses::SPECIES) && team[..index].iter()
+                .any(|other| std::ptr::eq(species_ref(other.pokemon.id), species))
+            {
+                violations.push(RuleViolation::SpeciesClause { index });
+            }
+            for mov in member.moves.iter().filter_map(|mov| *mov) {
+                if self.banned_moves.contains(&mov.id) {
+                    violations.push(
+                        RuleViolation::BannedMove { index, mov: mov.id });
+                }
+                if self.clauses.contains(Clauses::EVASION)
+                    && EVASION_MOVES.contains(&mov.id)
+                {
+                    violations.push(
+                        RuleViolation::EvasionClause { index, mov: mov.id });
+                }
+                if let Effect::OneHitKO = mov.effect {
+                    if self.clauses.contains(Clauses::OHKO) {
+                        violations.push(
+                            RuleViolation::OhkoClause { index, mov: mov.id });
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    pub fn prepare(&self, team: &Team) -> Team {
+        match self.level_scale {
+            Some(level) => team.iter().map(|member| {
+                let mut scaled = (**member).clone();
+                scaled.level = level;
+                Arc::new(scaled)
+            }).collect(),
+            None => team.clone(),
+        }
+    }
+
+    pub fn install(&self, hooks: &Hooks) {
+        if self.clauses.contains(Clauses::SLEEP) {
+            hooks.status_immunities.battle.borrow_mut().insert(
+                HookKey::new_engine(0, hooks::ENGINE_SLEEP_CLAUSE, 0),
+                StatusHook(sleep_clause));
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use crate::exec::moves::is_implemented;
+use crate::exec::turn::{Action, BattleOutcome};
+use crate::formats::SingleBattle;
+use crate::preview::TeamPreview;
+use crate::protocol::{ClientMessage, ServerMessage};
+use crate::rules::Ruleset;
+use crate::team::Team;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+pub const MAX_LINE_LENGTH: u64 = 4096;
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    pub fn send(&mut self, message: &ServerMessage) -> io::Result<()> {
+        writeln!(self.writer, "{}", message.encode())
+    }
+
+    pub fn error(&mut self, message: &str) -> io::Result<()> {
+        self.send(&ServerMessage::Error(message.to_string()))
+    }
+
+    pub fn receive(&mut self) -> io::Result<ClientMessage> {
+        loop {
+            let mut line = String::new();
+            let read = (&mut self.reader).take(MAX_LINE_LENGTH)
+                .read_line(&mut line)?;
+            if read == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof, "client disconnected"));
+            }
+            if !line.ends_with('\n') && read as u64 == MAX_LINE_LENGTH {
+                self.error("line too long")?;
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData, "line too long"));
+            }
+            match ClientMessage::decode(&line) {
+                Some(message) => return Ok(message),
+                None => self.error("unrecognized message")?,
+            }
+        }
+    }
+}
+
+fn check_team(team: &Team, rules: &Ruleset) -> Result<(), String> {
+    if let Some(violation) = rules.validate(team, false).first() {
+        return Err(format!("{:?}", violation));
+    }
+    for (index, member) in team.iter().enumerate() {
+        let mut moves = member.moves.iter().filter_map(|mov| *mov);
+        if let Some(mov) = moves.find(|mov| !is_implemented(mov)) {
+            return Err(format!(
+                "UnimplementedMove {{ index: {}, mov: {:?} }}", index, mov.id));
+        }
+    }
+    Ok(())
+}
+
+fn receive_team(
+    connection: &mut Connection, rules: &Ruleset
+) -> io::Result<Team> {
+    loop {
+        match connection.receive()? {
+            ClientMessage::Team(team) => match check_team(&team, rules) {
+                Ok(()) => return Ok(team),
+                Err(message) => connection.error(&message)?,
+            },
+            _ => connection.error("expected a team")?,
+        }
+    }
+}
+
+fn receive_order(
+    preview: &mut TeamPreview, side: usize, connection: &mut Connection
+) -> io::Result<()> {
+    loop {
+        match connection.receive()? {
+            ClientMessage::Order(order) => match preview.choose(side, order) {
+                Ok(()) => return Ok(()),
+                Err(err) => connection.error(&format!("{:?}", err))?,
+            },
+            _ => connection.error("expected an order")?,
+        }
+    }
+}
+
+fn start_battle(
+    teams: &[Team], rules: &Ruleset, connections: &mut [Connection; 2]
+) -> io::Result<SingleBattle> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    if !rules.team_preview {
+        return SingleBattle::new_validated(
+            &teams[0], &teams[1], rules.clone(), rand::random())
+            .map_err(|violations| invalid(format!("{:?}", violations)));
+    }
+    let mut preview = TeamPreview::new(&teams[0], &teams[1], rules.clone());
+    for (side, connection) in connections.iter_mut().enumerate() {
+        connection.send(&ServerMessage::Preview {
+            species: preview.revealed(1 - side),
+            picks: preview.picks(side),
+        })?;
+    }
+    for (side, connection) in connections.iter_mut().enumerate() {
+        receive_order(&mut preview, side, connection)?;
+    }
+    preview.start().map_err(|err| invalid(format!("{:?}", err)))
+}
+
+fn turn_request(battle: &SingleBattle, side: usize) -> ServerMessage {
+    let battler = battle.battler(side);
+    let mut moves = [None; 4];
+    {
+        let current = battler.current.borrow();
+        let pp = current.perm.borrow().pp;
+        for (slot, mov) in moves.iter_mut().enumerate() {
+            *mov = current.overlay.moves[slot].map(|mov| (mov.id, pp[slot]));
+        }
+    }
+    ServerMessage::TurnRequest { moves, switches: battler.switch_options() }
+}
+
+fn receive_action(
+    battle: &SingleBattle, side: usize, connection: &mut Connection
+) -> io::Result<Action> {
+    loop {
+        match connection.receive()? {
+            ClientMessage::Action(action) => {
+                match battle.battler(side).check_action(action) {
+                    Ok(()) => return Ok(action),
+                    Err(err) => connection.error(&format!("{:?}", err))?,
+                }
+            },
+            _ => connection.error("expected an action")?,
+        }
+        connection.send(&turn_request(battle, side))?;
+    }
+}
+
+fn receive_replacement(
+    battle: &mut SingleBattle, side: usize, connection: &mut Connection
+) -> io::Result<()> {
+    loop {
+        let switches = battle.battler(side).switch_options();
+        connection.send(&ServerMessage::ReplaceRequest { switches })?;
+        match connection.receive()? {
+            ClientMessage::Replace(index) => match battle.replace(side, index) {
+                Ok(()) => return Ok(()),
+                Err(err) => connection.error(&format!("{:?}", err))?,
+            },
+            _ => connection.error("expected a replacement")?,
+        }
+    }
+}
+
+fn play(
+    battle: &mut SingleBattle, connections: &mut [Connection; 2]
+) -> Result<BattleOutcome, (usize, io::Error)> {
+    loop {
+        for event in battle.drain_events() {
+            for (side, connection) in connections.iter_mut().enumerate() {
+                connection.send(&ServerMessage::Event(event))
+                    .map_err(|err| (side, err))?;
+            }
+        }
+        if let Some(outcome) = battle.outcome {
+            return Ok(outcome);
+        }
+        let pending = battle.pending_replacements();
+        if pending.iter().any(|pending| *pending) {
+            for (side, connection) in connections.iter_mut().enumerate() {
+                if pending[side] {
+                    receive_replacement(battle, side, connection)
+                        .map_err(|err| (side, err))?;
+                }
+            }
+            continue;
+        }
+        for (side, connection) in connections.iter_mut().enumerate() {
+            connection.send(&turn_request(battle, side))
+                .map_err(|err| (side, err))?;
+        }
+        let mut actions = [Action::Forfeit; 2];
+        for (side, connection) in connections.iter_mut().enumerate() {
+            actions[side] = receive_action(battle, side, connection)
+                .map_err(|err| (side, err))?;
+        }
+        if let Err(err) = battle.run_turn(actions[0], actions[1]) {
+            for connection in connections.iter_mut() {
+                let _ = connection.error(&format!("{:?}", err));
+            }
+        }
+    }
+}
+
+pub fn serve_match(
+    stream1: TcpStream, stream2: TcpStream, rules: &Ruleset
+) -> io::Result<BattleOutcome> {
+    let mut connections = [Connection::new(stream1)?, Connection::new(stream2)?];
+    let mut teams = Vec::new();
+    for (side, connection) in connections.iter_mut().enumerate() {
+        connection.send(&ServerMessage::Side(side))?;
+        teams.push(receive_team(connection, rules)?);
+    }
+    let mut battle = start_battle(&teams, rules, &mut connections)?;
+    let outcome = match play(&mut battle, &mut connections) {
+        Ok(outcome) => outcome,
+        Err((side, _)) => battle.forfeit(side),
+    };
+    for connection in connections.iter_mut() {
+        let _ = connection.send(&ServerMessage::End(outcome));
+    }
+    Ok(outcome)
+}
+
+pub fn run(listener: TcpListener, rules: Ruleset) -> io::Result<()> {
+    loop {
+        let (first, _) = listener.accept()?;
+        let (second, _) = listener.accept()?;
+        let rules = rules.clone();
+        thread::spawn(move || serve_match(first, second, &rules));
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+// A thread-safe stand-in for `Rc<RefCell<T>>`, so that a whole battle can be
+// sent to another thread. Unlike `RefCell`, a lock can't be re-entered: a
+// thread must never borrow a value whose guard it already holds, even just to
+// read it, so take what's needed out of a guard before calling anything that
+// might borrow the same value again.
+pub struct Shared<T>(Arc<RwLock<T>>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Shared(Arc::new(RwLock::new(value)))
+    }
+
+    pub fn borrow(&self) -> RwLockReadGuard<T> {
+        self.0.read().unwrap()
+    }
+
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<T> {
+        self.0.write().unwrap()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
@@ -0,0 +1,276 @@
+use crate::caches::names::{enum_name, names, normalize, pokemon_name};
+use crate::caches::pokemon::{pokemon_ref, species_ref};
+use crate::team::{Team, TeamMember};
+use std::str::FromStr;
+use std::sync::Arc;
+use vdex::Stat;
+use vdex::pokemon;
+
+// Pokémon Showdown's plain-text team format. Members are separated by blank
+// lines; anything Gen IV has no use for (Shiny, Tera Type, ...) is ignored.
+
+const STATS: [(Stat, &str); 6] = [
+    (Stat::HP, "HP"),
+    (Stat::Attack, "Atk"),
+    (Stat::Defense, "Def"),
+    (Stat::SpecialAttack, "SpA"),
+    (Stat::SpecialDefense, "SpD"),
+    (Stat::Speed, "Spe"),
+];
+
+const IGNORED_KEYS: [&str; 6] = [
+    "Shiny", "Pokeball", "Hidden Power", "Dynamax Level", "Gigantamax",
+    "Tera Type",
+];
+
+pub const DEFAULT_LEVEL: u8 = 100;
+pub const DEFAULT_FRIENDSHIP: u8 = 255;
+pub const DEFAULT_IV: u8 = 31;
+pub const DEFAULT_PP_UPS: u8 = 3;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ShowdownErrorKind {
+    MissingSpecies,
+    UnknownSpecies(String),
+    UnknownItem(String),
+    UnknownAbility(String),
+    UnknownNature(String),
+    UnknownMove(String),
+    UnknownStat(String),
+    InvalidNumber(String),
+    TooManyMoves,
+    UnrecognizedLine,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShowdownError {
+    pub line: usize,
+    pub kind: ShowdownErrorKind,
+}
+
+fn parse_number<T: FromStr>(text: &str) -> Result<T, ShowdownErrorKind> {
+    text.trim().parse()
+        .map_err(|_| ShowdownErrorKind::InvalidNumber(text.trim().to_string()))
+}
+
+fn parse_stats(
+    text: &str, stats: &mut pokemon::BaseStats
+) -> Result<(), ShowdownErrorKind> {
+    for part in text.split('/') {
+        let part = part.trim();
+        let split = part.find(' ')
+            .ok_or_else(|| ShowdownErrorKind::UnknownStat(part.to_string()))?;
+        let (value, name) = (&part[..split], part[split..].trim());
+        let index = STATS.iter().position(|(_, short)| *short == name)
+            .ok_or_else(|| ShowdownErrorKind::UnknownStat(name.to_string()))?;
+        stats.0[index] = parse_number(value)?;
+    }
+    Ok(())
+}
+
+fn format_stats(stats: &pokemon::BaseStats, default: u8) -> Option<String> {
+    let parts = STATS.iter().enumerate()
+        .filter(|(i, _)| stats.0[*i] != default)
+        .map(|(i, (_, name))| format!("{} {}", stats.0[i], name))
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" / "))
+    }
+}
+
+fn default_gender(pokemon_id: pokemon::PokemonId) -> pokemon::Gender {
+    match species_ref(pokemon_id).gender_rate {
+        rate if rate < 0 => pokemon::Gender::Genderless,
+        8 => pokemon::Gender::Female,
+        _ => pokemon::Gender::Male,
+    }
+}
+
+struct Header {
+    pokemon: pokemon::PokemonId,
+    gender: Option<pokemon::Gender>,
+    held: Option<&'static vdex::items::Item>,
+}
+
+fn parse_header(line: &str) -> Result<Header, ShowdownErrorKind> {
+    let dex = vdex::pokedex();
+    let (mut name, held) = match line.rfind(" @ ") {
+        Some(at) => {
+            let item = line[at + 3..].trim();
+            let id = names().items.get(&normalize(item)).ok_or_else(
+                || ShowdownErrorKind::UnknownItem(item.to_string()))?;
+            (line[..at].trim(), Some(&dex.items[*id]))
+        },
+        None => (line.trim(), None),
+    };
+    let mut gender = None;
+    if name.ends_with(" (M)") || name.ends_with(" (F)") {
+        gender = Some(if name.ends_with("(M)") {
+            pokemon::Gender::Male
+        } else {
+            pokemon::Gender::Female
+        });
+        name = name[..name.len() - 4].trim_end();
+    }
+    if name.ends_with(')') {
+        if let Some(open) = name.rfind('(') {
+            name = &name[open + 1..name.len() - 1];
+        }
+    }
+    let pokemon = *names().pokemon.get(&normalize(name))
+        .ok_or_else(|| ShowdownErrorKind::UnknownSpecies(name.to_string()))?;
+    Ok(Header { pokemon, gender, held })
+}
+
+fn import_member(
+    lines: &[(usize, &str)], errors: &mut Vec<ShowdownError>
+) -> Option<TeamMember> {
+    let dex = vdex::pokedex();
+    let error_count = errors.len();
+    let mut report = |line: usize, kind| {
+        errors.push(ShowdownError { line, kind });
+    };
+    let header = match lines.first() {
+        Some((number, line)) => match parse_header(line) {
+            Ok(header) => header,
+            Err(kind) => {
+                report(*number, kind);
+                return None;
+            },
+        },
+        None => return None,
+    };
+    let pokemon = pokemon_ref(header.pokemon);
+    let mut member = TeamMember {
+        pokemon,
+        gender: header.gender.unwrap_or_else(|| default_gender(pokemon.id)),
+        ability: pokemon.abilities.first(),
+        nature: vdex::Nature::Serious,
+        held: header.held,
+        friendship: DEFAULT_FRIENDSHIP,
+        evs: pokemon::BaseStats([0; 6]),
+        ivs: pokemon::BaseStats([DEFAULT_IV; 6]),
+        moves: [None; 4],
+        pp_ups: [DEFAULT_PP_UPS; 4],
+        level: DEFAULT_LEVEL,
+    };
+    let mut move_count = 0;
+    for (number, line) in lines[1..].iter() {
+        let result = if line.starts_with('-') {
+            let mut name = line[1..].trim();
+            if let Some(open) = name.find('[') {
+                name = name[..open].trim();
+            }
+            match names().moves.get(&normalize(name)) {
+                Some(_) if move_count >= member.moves.len()
+                    => Err(ShowdownErrorKind::TooManyMoves),
+                Some(id) => {
+                    member.moves[move_count] = Some(&dex.moves[*id]);
+                    move_count += 1;
+                    Ok(())
+                },
+                None => Err(ShowdownErrorKind::UnknownMove(name.to_string())),
+            }
+        } else if line.ends_with(" Nature") {
+            let name = line[..line.len() - " Nature".len()].trim();
+            names().natures.get(&normalize(name))
+                .map(|nature| member.nature = *nature)
+                .ok_or_else(
+                    || ShowdownErrorKind::UnknownNature(name.to_string()))
+        } else if let Some(colon) = line.find(':') {
+            let value = line[colon + 1..].trim();
+            match &line[..colon] {
+                "Ability" => names().abilities.get(&normalize(value))
+                    .map(|ability| member.ability = *ability)
+                    .ok_or_else(|| ShowdownErrorKind::UnknownAbility(
+                        value.to_string())),
+                "Level" => parse_number(value)
+                    .map(|level| member.level = level),
+                "Happiness" => parse_number(value)
+                    .map(|friendship| member.friendship = friendship),
+                "EVs" => parse_stats(value, &mut member.evs),
+                "IVs" => parse_stats(value, &mut member.ivs),
+                key if IGNORED_KEYS.contains(&key) => Ok(()),
+                _ => Err(ShowdownErrorKind::UnrecognizedLine),
+            }
+        } else {
+            Err(ShowdownErrorKind::UnrecognizedLine)
+        };
+        if let Err(kind) = result {
+            report(*number, kind);
+        }
+    }
+    if errors.len() > error_count {
+        None
+    } else {
+        Some(member)
+    }
+}
+
+pub fn import(text: &str) -> Result<Team, Vec<ShowdownError>> {
+    let mut team = Team::new();
+    let mut errors = Vec::new();
+    let mut lines = Vec::new();
+    let numbered = text.lines().map(|line| line.trim()).enumerate()
+        .map(|(i, line)| (i + 1, line)).chain(Some((0, "")));
+    for (number, line) in numbered {
+        if !line.is_empty() {
+            lines.push((number, line));
+        } else if !lines.is_empty() {
+            if let Some(member) = import_member(&lines, &mut errors) {
+                team.push(Arc::new(member));
+            }
+            lines.clear();
+        }
+    }
+    if team.is_empty() && errors.is_empty() {
+        errors.push(ShowdownError {
+            line: 1,
+            kind: ShowdownErrorKind::MissingSpecies,
+        });
+    }
+    if errors.is_empty() {
+        Ok(team)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn export_member(member: &TeamMember) -> String {
+    let mut lines = Vec::new();
+    let mut header = pokemon_name(member.pokemon.id);
+    match member.gender {
+        pokemon::Gender::Male => header.push_str(" (M)"),
+        pokemon::Gender::Female => header.push_str(" (F)"),
+        pokemon::Gender::Genderless => (),
+    }
+    if let Some(item) = member.held {
+        header.push_str(&format!(" @ {}", item.name));
+    }
+    lines.push(header);
+    lines.push(format!("Ability: {}", enum_name(member.ability)));
+    if member.level != DEFAULT_LEVEL {
+        lines.push(format!("Level: {}", member.level));
+    }
+    if member.friendship != DEFAULT_FRIENDSHIP {
+        lines.push(format!("Happiness: {}", member.friendship));
+    }
+    if let Some(evs) = format_stats(&member.evs, 0) {
+        lines.push(format!("EVs: {}", evs));
+    }
+    lines.push(format!("{} Nature", enum_name(member.nature)));
+    if let Some(ivs) = format_stats(&member.ivs, DEFAULT_IV) {
+        lines.push(format!("IVs: {}", ivs));
+    }
+    for mov in member.moves.iter().filter_map(|mov| *mov) {
+        lines.push(format!("- {}", mov.name));
+    }
+    lines.join("\n")
+}
+
+pub fn export(team: &Team) -> String {
+    team.iter().map(|member| export_member(member))
+        .collect::<Vec<_>>().join("\n\n")
+}
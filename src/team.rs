@@ -1,12 +1,58 @@
 use crate::caches::movesets::cache_moveset;
-use crate::caches::pokemon::species_ref;
-use std::rc::Rc;
-use vdex::{Ability, Nature, Stat};
+use crate::caches::pokemon::{pokemon_ref, pokemon_ref_map, species_ref};
+use std::sync::Arc;
+use vdex::{Ability, Enum, Nature, Stat};
 use vdex::items;
 use vdex::moves;
 use vdex::pokemon;
 
-pub type Team = Vec<Rc<TeamMember>>;
+pub type Team = Vec<Arc<TeamMember>>;
+
+pub const MAX_TEAM_SIZE: usize = 6;
+pub const MAX_EV_TOTAL: u16 = 510;
+pub const MAX_IV: u8 = 31;
+pub const MAX_PP_UPS: u8 = 3;
+pub const MAX_LEVEL: u8 = 100;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Violation {
+    IllegalGender,
+    IllegalAbility,
+    UnholdableItem { item: items::ItemId },
+    EvTotalExceeded { total: u16 },
+    IvOutOfRange { stat: Stat, iv: u8 },
+    TooManyPpUps { slot: u8, pp_ups: u8 },
+    IllegalLevel { level: u8 },
+    NoMoves,
+    DuplicateMove { mov: moves::MoveId },
+    MoveNotLearnable { mov: moves::MoveId },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TeamViolation {
+    Empty,
+    TooLarge { size: usize },
+    Member { index: usize, violation: Violation },
+}
+
+pub fn validate_team(team: &Team, skip_moveset: bool) -> Vec<TeamViolation> {
+    let mut violations = Vec::new();
+    if team.is_empty() {
+        violations.push(TeamViolation::Empty);
+    } else if team.len() > MAX_TEAM_SIZE {
+        violations.push(TeamViolation::TooLarge { size: team.len() });
+    }
+    for (index, member) in team.iter().enumerate() {
+        for violation in member.validate(skip_moveset) {
+            violations.push(TeamViolation::Member { index, violation });
+        }
+    }
+    violations
+}
+
+pub fn verify_team(team: &Team, skip_moveset: bool) -> bool {
+    validate_team(team, skip_moveset).is_empty()
+}
 
 #[derive(Copy, Clone, Debug)]
 pub struct TeamMember {
@@ -58,29 +104,168 @@ impl TeamMember {
     }
 
     pub fn verify(&self, skip_moveset: bool) -> bool {
+        self.validate(skip_moveset).is_empty()
+    }
+
+    pub fn validate(&self, skip_moveset: bool) -> Vec<Violation> {
+        let mut violations = Vec::new();
         let genderless = self.gender == pokemon::Gender::Genderless;
         let species_genderless = species_ref(self.pokemon.id).gender_rate < 0;
-        let gender = genderless == species_genderless;
+        if genderless != species_genderless {
+            violations.push(Violation::IllegalGender);
+        }
         let abilities = self.pokemon.abilities;
-        let ability = abilities.first() == self.ability
-            || abilities.second().map_or(false, |a| a == self.ability);
-        let held = self.held.map_or(true,
-            |item| item.flags.contains(items::Flags::HOLDABLE));
-        let evs = self.evs.0.iter().fold(0, |acc, ev| acc + (*ev as u16)) <= 510;
-        let ivs = !self.ivs.0.iter().any(|iv| *iv > 31);
-        let pp_ups = self.pp_ups.iter().all(|ups| *ups <= 3);
-        let level = self.level > 0 && self.level <= 100;
-        gender && ability && held && evs && ivs && pp_ups && level
-            && self.verify_moves(skip_moveset)
+        if abilities.first() != self.ability
+            && abilities.second().map_or(true, |a| a != self.ability)
+        {
+            violations.push(Violation::IllegalAbility);
+        }
+        if let Some(item) = self.held {
+            if !item.flags.contains(items::Flags::HOLDABLE) {
+                violations.push(Violation::UnholdableItem { item: item.id });
+            }
+        }
+        let total = self.evs.0.iter().fold(0, |acc, ev| acc + (*ev as u16));
+        if total > MAX_EV_TOTAL {
+            violations.push(Violation::EvTotalExceeded { total });
+        }
+        for (i, iv) in self.ivs.0.iter().enumerate() {
+            if *iv > MAX_IV {
+                violations.push(Violation::IvOutOfRange {
+                    stat: Stat::from_repr(i as _).unwrap(),
+                    iv: *iv,
+                });
+            }
+        }
+        for (slot, ups) in self.pp_ups.iter().enumerate() {
+            if *ups > MAX_PP_UPS {
+                violations.push(Violation::TooManyPpUps {
+                    slot: slot as u8,
+                    pp_ups: *ups,
+                });
+            }
+        }
+        if self.level == 0 || self.level > MAX_LEVEL {
+            violations.push(Violation::IllegalLevel { level: self.level });
+        }
+        self.validate_moves(skip_moveset, &mut violations);
+        violations
     }
 
-    fn verify_moves(&self, skip_moveset: bool) -> bool {
-        let mut list = self.moves.iter().filter_map(
+    fn validate_moves(
+        &self, skip_moveset: bool, violations: &mut Vec<Violation>
+    ) {
+        let list = self.moves.iter().filter_map(
             |opt| opt.map(|mov| mov.id)).collect::<Vec<_>>();
-        list.sort_unstable();
-        let count = list.len();
-        list.dedup();
-        count > 0 && list.len() == count && (skip_moveset || list.iter().all(
-            |mov| cache_moveset(self.pokemon.id, |set| set.contains(&mov))))
+        if list.is_empty() {
+            violations.push(Violation::NoMoves);
+        }
+        for (i, mov) in list.iter().enumerate() {
+            if list[..i].contains(mov) {
+                violations.push(Violation::DuplicateMove { mov: *mov });
+            } else if !skip_moveset
+                && !cache_moveset(self.pokemon.id, |set| set.contains(mov))
+            {
+                violations.push(Violation::MoveNotLearnable { mov: *mov });
+            }
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        // A compact, id-based line format for replays and the wire protocol.
+        let stats = |stats: &pokemon::BaseStats| stats.0.iter()
+            .map(|value| value.to_string()).collect::<Vec<_>>().join(",");
+        let moves = self.moves.iter().map(|opt| opt.map_or(
+            "-".to_string(), |mov| mov.id.0.to_string()))
+            .collect::<Vec<_>>().join(",");
+        let pp_ups = self.pp_ups.iter().map(|ups| ups.to_string())
+            .collect::<Vec<_>>().join(",");
+        format!("{} {} {} {} {} {} {} {} {} {} {}",
+            self.pokemon.id.0,
+            match self.gender {
+                pokemon::Gender::Female => "F",
+                pokemon::Gender::Male => "M",
+                pokemon::Gender::Genderless => "N",
+            },
+            self.ability.repr(),
+            self.nature.repr(),
+            self.held.map_or("-".to_string(), |item| item.id.0.to_string()),
+            self.friendship,
+            stats(&self.evs),
+            stats(&self.ivs),
+            moves,
+            pp_ups,
+            self.level)
+    }
+
+    pub fn decode(line: &str) -> Option<TeamMember> {
+        let dex = vdex::pokedex();
+        let mut tokens = line.split_whitespace();
+        let pokemon_id = pokemon::PokemonId(tokens.next()?.parse().ok()?);
+        if !pokemon_ref_map().contains_key(&pokemon_id) {
+            return None;
+        }
+        let gender = match tokens.next()? {
+            "F" => pokemon::Gender::Female,
+            "M" => pokemon::Gender::Male,
+            "N" => pokemon::Gender::Genderless,
+            _ => return None,
+        };
+        let ability = Ability::from_repr(tokens.next()?.parse().ok()?)?;
+        let nature = Nature::from_repr(tokens.next()?.parse().ok()?)?;
+        let held = match tokens.next()? {
+            "-" => None,
+            id => {
+                let id = id.parse().ok()?;
+                if id as usize >= items::ITEM_COUNT {
+                    return None;
+                }
+                Some(&dex.items[items::ItemId(id)])
+            },
+        };
+        let friendship = tokens.next()?.parse().ok()?;
+        let mut stats = || -> Option<pokemon::BaseStats> {
+            let mut values = [0; 6];
+            let mut split = tokens.next()?.split(',');
+            for value in values.iter_mut() {
+                *value = split.next()?.parse().ok()?;
+            }
+            Some(pokemon::BaseStats(values))
+        };
+        let evs = stats()?;
+        let ivs = stats()?;
+        let mut move_slots = [None; 4];
+        let mut split = tokens.next()?.split(',');
+        for mov in move_slots.iter_mut() {
+            *mov = match split.next()? {
+                "-" => None,
+                id => {
+                    let id = id.parse().ok()?;
+                    if id as usize >= moves::MOVE_COUNT {
+                        return None;
+                    }
+                    Some(&dex.moves[moves::MoveId(id)])
+                },
+            };
+        }
+        let mut pp_ups = [0; 4];
+        let mut split = tokens.next()?.split(',');
+        for ups in pp_ups.iter_mut() {
+            *ups = split.next()?.parse().ok()?;
+        }
+        let level = tokens.next()?.parse().ok()?;
+        Some(TeamMember {
+            pokemon: pokemon_ref(pokemon_id),
+            gender,
+            ability,
+            nature,
+            held,
+            friendship,
+            evs,
+            ivs,
+            moves: move_slots,
+            pp_ups,
+            level,
+        })
     }
 }
@@ -1,18 +1,35 @@
-use crate::caches::pokemon::pokemon_ref;
-use crate::exec::moves::execute_move;
+use crate::ailments;
+use crate::battle::DamageContext;
+use crate::caches::names::{names, normalize, pokemon_name};
+use crate::caches::pokemon::{pokemon_ref, species_ref};
+use crate::client::{Client, Update};
+use crate::events::Event;
+use crate::exec::moves::{execute_move, is_implemented};
+use crate::exec::premove::{self, check_move, MoveBlock};
+use crate::exec::residual;
+use crate::exec::turn::{Action, ActionError, BattleOutcome};
+use crate::field::{Hazard, SideCondition, Weather};
 use crate::formats::SingleBattle;
 use crate::hooks;
+use crate::items::{self, HeldItem};
+use crate::preview::{PreviewError, TeamPreview};
+use crate::replay::Replay;
+use crate::rules::{self, Ruleset};
+use crate::server;
+use crate::showdown;
 use crate::team;
-use std::rc::Rc;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::Arc;
+use std::thread;
 use vdex::Ability;
+use vdex::Enum;
 use vdex::moves;
 use vdex::Nature;
 use vdex::pokemon;
 
-#[test]
-fn test_move() {
+fn clefairy() -> team::TeamMember {
     let dex = vdex::pokedex();
-    let clefairy = team::TeamMember {
+    team::TeamMember {
         pokemon: pokemon_ref(pokemon::PokemonId(34)),
         gender: pokemon::Gender::Female,
         ability: Ability::MagicGuard,
@@ -24,9 +41,26 @@ fn test_move() {
         moves: [Some(&dex.moves[moves::MoveId(0)]), None, None, None],
         pp_ups: Default::default(),
         level: 5,
-    };
+    }
+}
+
+// Tests use a fixed seed so that their rolls are the same on every run.
+const SEED: u64 = 0;
+
+fn seeded(team1: &team::Team, team2: &team::Team) -> SingleBattle {
+    SingleBattle::new_seeded(team1, team2, Ruleset::default(), SEED)
+}
+
+fn without_criticals(battle: &SingleBattle) {
+    battle.hooks.critical_cancels.battle.borrow_mut().insert(
+        hooks::HookKey::new_engine(0, 0, 0), true);
+}
+
+#[test]
+fn test_move() {
+    let clefairy = clefairy();
     assert!(clefairy.verify(false));
-    let team = vec![Rc::new(clefairy)];
+    let team = vec![Arc::new(clefairy)];
     let battle = SingleBattle::new(&team, &team);
     battle.hooks.critical_cancels.battle.borrow_mut().insert(
         hooks::HookKey::new_engine(0, 0, 0), true);
@@ -42,3 +76,758 @@ fn test_move() {
     eprintln!("Target HP: {}", final_hp);
     assert!(final_hp >= 15 && final_hp <= 17);
 }
+
+#[test]
+fn test_unimplemented() {
+    let dex = vdex::pokedex();
+    let counter = &dex.moves[moves::MoveId(67)];
+    let metronome = &dex.moves[moves::MoveId(117)];
+    assert!(!is_implemented(counter));
+    assert!(!is_implemented(metronome));
+    assert!(is_implemented(&dex.moves[moves::MoveId(0)]));
+    let team = vec![Arc::new(clefairy())];
+    let battle = seeded(&team, &team);
+    let user = &battle.battler1.current;
+    let mut rng = battle.rng.clone();
+    for mov in [counter, metronome].iter() {
+        assert!(!execute_move(user, 4, *mov,
+            |tgts| battle.resolve_targets(tgts), &mut rng));
+    }
+}
+
+#[test]
+fn test_struggle() {
+    let dex = vdex::pokedex();
+    let mut protector = clefairy();
+    protector.moves[1] = Some(&dex.moves[moves::MoveId(181)]);
+    let team = vec![Arc::new(protector)];
+    let battle = seeded(&team, &team);
+    assert_eq!(battle.battler1.check_action(Action::Move(1)),
+        Err(ActionError::UnimplementedMove));
+    let user = &battle.battler1.current;
+    let target = &battle.battler2.current;
+    user.borrow().perm.borrow_mut().pp = [0; 4];
+    assert_eq!(battle.battler1.check_action(Action::Move(0)), Ok(()));
+    let max_hp = user.borrow().overlay.stat(vdex::Stat::HP);
+    let mut rng = battle.rng.clone();
+    assert_eq!(battle.use_move(0, 0, &mut rng), Ok(true));
+    assert_eq!(user.borrow().perm.borrow().hp, max_hp - (max_hp / 4).max(1));
+    assert!(target.borrow().perm.borrow().hp < max_hp);
+    assert_eq!(user.borrow().perm.borrow().pp, [0; 4]);
+}
+
+#[test]
+fn test_validate() {
+    let clefairy = clefairy();
+    assert!(clefairy.validate(false).is_empty());
+    let mut illegal = clefairy;
+    illegal.evs = pokemon::BaseStats([255, 255, 1, 0, 0, 0]);
+    illegal.moves[1] = illegal.moves[0];
+    assert_eq!(illegal.validate(false), vec![
+        team::Violation::EvTotalExceeded { total: 511 },
+        team::Violation::DuplicateMove { mov: moves::MoveId(0) },
+    ]);
+    assert_eq!(team::validate_team(&Vec::new(), false),
+        vec![team::TeamViolation::Empty]);
+}
+
+#[test]
+fn test_turn() {
+    let team = vec![Arc::new(clefairy())];
+    let mut battle = seeded(&team, &team);
+    let result = battle.run_turn(Action::Move(0), Action::Move(0));
+    assert_eq!(result, Ok(None));
+    for side in 0..2 {
+        let current = battle.battler(side).current.borrow();
+        assert_eq!(current.perm.borrow().pp[0], 34);
+    }
+    assert!(battle.run_turn(Action::Move(1), Action::Move(0)).is_err());
+    let result = battle.run_turn(Action::Move(0), Action::Forfeit);
+    assert_eq!(result, Ok(Some(BattleOutcome::Battler1Wins)));
+}
+
+#[test]
+fn test_switch() {
+    let team = vec![Arc::new(clefairy()), Arc::new(clefairy())];
+    let mut battle = seeded(&team, &team);
+    battle.battler1.current.borrow_mut().stat_changes[1] = 2;
+    assert_eq!(battle.battler1.check_action(Action::Switch(0)),
+        Err(ActionError::AlreadyActive));
+    assert_eq!(battle.battler1.check_action(Action::Switch(2)),
+        Err(ActionError::InvalidSwitch));
+    let result = battle.run_turn(Action::Switch(1), Action::Move(0));
+    assert_eq!(result, Ok(None));
+    let current = battle.battler1.current.borrow();
+    assert_eq!(current.index, 1);
+    assert_eq!(current.stat_changes[1], 0);
+}
+
+#[test]
+fn test_faint() {
+    let team1 = vec![Arc::new(clefairy())];
+    let team2 = vec![Arc::new(clefairy()), Arc::new(clefairy())];
+    let mut battle = seeded(&team1, &team2);
+    battle.battler2.current.borrow().perm.borrow_mut().hp = 1;
+    let result = battle.run_turn(Action::Move(0), Action::Move(0));
+    assert_eq!(result, Ok(None));
+    assert_eq!(battle.pending_replacements(), [false, true]);
+    assert_eq!(battle.run_turn(Action::Move(0), Action::Move(0)),
+        Err(ActionError::ReplacementPending));
+    assert_eq!(battle.replace(1, 1), Ok(()));
+    battle.battler2.current.borrow().perm.borrow_mut().hp = 1;
+    let result = battle.run_turn(Action::Move(0), Action::Move(0));
+    assert_eq!(result, Ok(Some(BattleOutcome::Battler1Wins)));
+}
+
+#[test]
+fn test_confusion_damage() {
+    let dex = vdex::pokedex();
+    let ember = &dex.moves[moves::MoveId(51)];
+    let plain = vec![Arc::new(clefairy())];
+    let charcoal = vec![Arc::new(holding("charcoal"))];
+    let battle = seeded(&plain, &charcoal);
+    let damage = |current| {
+        DamageContext::new_confusion(current, ember).calc_max_damage()
+    };
+    let base = damage(&battle.battler1.current);
+    assert_eq!(damage(&battle.battler2.current), base);
+    battle.battler1.current.borrow().set_weather(Weather::Sun, None);
+    assert_eq!(damage(&battle.battler1.current), base);
+    battle.battler1.current.borrow().set_weather(Weather::Rain, None);
+    assert_eq!(damage(&battle.battler1.current), base);
+}
+
+#[test]
+fn test_confusion_modifiers() {
+    let dex = vdex::pokedex();
+    let pound = &dex.moves[moves::MoveId(0)];
+    let mut technician = clefairy();
+    technician.ability = Ability::Technician;
+    let team1 = vec![Arc::new(clefairy())];
+    let team2 = vec![Arc::new(technician), Arc::new(holding("choiceband"))];
+    let mut battle = seeded(&team1, &team2);
+    let damage = |current| {
+        DamageContext::new_confusion(current, pound).calc_max_damage()
+    };
+    let base = damage(&battle.battler1.current);
+    assert_eq!(damage(&battle.battler2.current), base);
+    battle.battler2.switch(1).unwrap();
+    assert_eq!(damage(&battle.battler2.current), base);
+}
+
+#[test]
+fn test_status() {
+    let dex = vdex::pokedex();
+    let mut user = clefairy();
+    user.moves[1] = Some(&dex.moves[moves::MoveId(85)]);
+    let mut limber = clefairy();
+    limber.ability = Ability::Limber;
+    let mut charmander = clefairy();
+    charmander.pokemon = pokemon_ref(pokemon::PokemonId(3));
+    let team1 = vec![Arc::new(user)];
+    let team2 = vec![
+        Arc::new(clefairy()), Arc::new(limber), Arc::new(charmander)];
+    let mut battle = seeded(&team1, &team2);
+    let mut rng = battle.rng.clone();
+    battle.use_move(0, 1, &mut rng).unwrap();
+    {
+        let mut target = battle.battler2.current.borrow_mut();
+        assert!(target.is_paralyzed());
+        assert!(!target.inflict(ailments::BenchAilment::Burned));
+        assert!(target.is_paralyzed());
+    }
+    battle.battler2.switch(1).unwrap();
+    battle.use_move(0, 1, &mut rng).unwrap();
+    assert!(battle.battler2.current.borrow().perm.borrow().status.is_none());
+    battle.battler2.switch(2).unwrap();
+    let mut target = battle.battler2.current.borrow_mut();
+    assert!(!target.inflict(ailments::BenchAilment::Burned));
+    assert!(target.inflict(ailments::BenchAilment::Poisoned { bad: true }));
+    assert!(target.is_poisoned());
+}
+
+#[test]
+fn test_residual() {
+    let mut plain = clefairy();
+    plain.ability = Ability::CuteCharm;
+    let team1 = vec![Arc::new(clefairy())];
+    let team2 = vec![Arc::new(plain)];
+    let mut battle = seeded(&team1, &team2);
+    {
+        let mut target = battle.battler2.current.borrow_mut();
+        target.inflict(ailments::BenchAilment::Poisoned { bad: true });
+        target.status.flags.insert(ailments::BattlerAilmentFlags::SEEDED
+            | ailments::BattlerAilmentFlags::CURSED);
+    }
+    let sleep = ailments::BenchAilment::Asleep { remaining_turns: 1 };
+    battle.battler1.current.borrow().perm.borrow_mut().status = sleep;
+    let start_hp = battle.battler2.current.borrow().perm.borrow().hp;
+    battle.drain_events();
+    let mut rng = battle.rng.clone();
+    battle.end_turn(&mut rng);
+    if let ailments::BenchAilment::Asleep { remaining_turns }
+        = battle.battler1.current.borrow().perm.borrow().status
+    {
+        assert_eq!(remaining_turns, 1);
+    } else {
+        panic!("the sleep counter should only drop when trying to move");
+    }
+    let kinds: Vec<_> = battle.drain_events().iter().filter_map(|event| {
+        if let Event::Residual { kind, .. } = event { Some(*kind) } else { None }
+    }).collect();
+    assert_eq!(kinds, vec![residual::Residual::LeechSeed,
+        residual::Residual::Poison, residual::Residual::Curse]);
+    let hp = battle.battler2.current.borrow().perm.borrow().hp;
+    assert!(hp < start_hp);
+    let turns = |battle: &SingleBattle| {
+        battle.battler2.current.borrow().status.turns_badly_poisoned
+    };
+    assert_eq!(turns(&battle), 1);
+    battle.end_turn(&mut rng);
+    assert_eq!(turns(&battle), 2);
+}
+
+#[test]
+fn test_premove() {
+    let dex = vdex::pokedex();
+    let pound = &dex.moves[moves::MoveId(0)];
+    let flame_wheel = &dex.moves[premove::THAWING_MOVES[0]];
+    let team = vec![Arc::new(clefairy())];
+    let battle = seeded(&team, &team);
+    let user = &battle.battler1.current;
+    let mut rng = battle.rng.clone();
+    let set_status = |status| user.borrow().perm.borrow_mut().status = status;
+    set_status(ailments::BenchAilment::Asleep { remaining_turns: 2 });
+    assert_eq!(check_move(user, pound, &mut rng), Err(MoveBlock::Asleep));
+    assert_eq!(check_move(user, pound, &mut rng), Err(MoveBlock::Asleep));
+    assert_eq!(check_move(user, pound, &mut rng), Ok(()));
+    assert!(!user.borrow().is_asleep());
+    set_status(ailments::BenchAilment::Frozen);
+    assert_eq!(check_move(user, flame_wheel, &mut rng), Ok(()));
+    assert!(!user.borrow().is_frozen());
+    set_status(ailments::BenchAilment::Paralyzed);
+    let paralyzed = (0..100).filter(|_| {
+        check_move(user, pound, &mut rng) == Err(MoveBlock::FullyParalyzed)
+    }).count();
+    assert!(paralyzed > 0 && paralyzed < 100);
+    set_status(ailments::BenchAilment::None);
+    let max_hp = user.borrow().overlay.stat(vdex::Stat::HP);
+    let mut confused = 0;
+    for _ in 0..100 {
+        {
+            let mut user = user.borrow_mut();
+            user.perm.borrow_mut().hp = max_hp;
+            user.status.flags.insert(ailments::BattlerAilmentFlags::CONFUSED);
+            user.status.remaining_confused_attacks = 1;
+        }
+        if let Err(reason) = check_move(user, pound, &mut rng) {
+            assert_eq!(reason, MoveBlock::Confused);
+            assert!(user.borrow().perm.borrow().hp < max_hp);
+            confused += 1;
+        }
+    }
+    assert!(confused > 0);
+    assert_eq!(check_move(user, pound, &mut rng), Ok(()));
+    assert!(!user.borrow().is_confused());
+}
+
+#[test]
+fn test_flinch() {
+    let mut steadfast = clefairy();
+    steadfast.ability = Ability::Steadfast;
+    let mut inner_focus = clefairy();
+    inner_focus.ability = Ability::InnerFocus;
+    let team1 = vec![Arc::new(clefairy())];
+    let team2 = vec![Arc::new(steadfast), Arc::new(inner_focus)];
+    let mut battle = seeded(&team1, &team2);
+    let mut rng = battle.rng.clone();
+    let speed = vdex::Stat::Speed.repr() as usize;
+    assert!(battle.battler2.current.borrow_mut().flinch());
+    assert_eq!(battle.use_move(1, 0, &mut rng), Err(MoveBlock::Flinched));
+    assert_eq!(battle.battler2.current.borrow().stat_changes[speed], 1);
+    assert!(!battle.battler2.current.borrow_mut().flinch());
+    battle.end_turn(&mut rng);
+    assert!(!battle.battler2.current.borrow().flinched);
+    {
+        let mut target = battle.battler2.current.borrow_mut();
+        target.status.flags
+            .insert(ailments::BattlerAilmentFlags::SUBSTITUTED);
+        assert!(!target.flinch());
+    }
+    battle.battler2.switch(1).unwrap();
+    assert!(!battle.battler2.current.borrow_mut().flinch());
+}
+
+#[test]
+fn test_stat_changes() {
+    let dex = vdex::pokedex();
+    let mut user = clefairy();
+    user.moves[1] = Some(&dex.moves[moves::MoveId(44)]);
+    user.moves[2] = Some(&dex.moves[moves::MoveId(53)]);
+    user.moves[3] = Some(&dex.moves[moves::MoveId(13)]);
+    let mut hyper_cutter = user;
+    hyper_cutter.ability = Ability::HyperCutter;
+    let team1 = vec![Arc::new(user)];
+    let team2 = vec![Arc::new(hyper_cutter), Arc::new(user)];
+    let mut battle = seeded(&team1, &team2);
+    let mut rng = battle.rng.clone();
+    let attack = vdex::Stat::Attack.repr() as usize;
+    let foe_attack = |battle: &SingleBattle| {
+        battle.battler2.current.borrow().stat_changes[attack]
+    };
+    battle.use_move(0, 1, &mut rng).unwrap();
+    assert_eq!(foe_attack(&battle), 0);
+    battle.battler2.switch(1).unwrap();
+    battle.use_move(0, 1, &mut rng).unwrap();
+    assert_eq!(foe_attack(&battle), -1);
+    battle.use_move(1, 2, &mut rng).unwrap();
+    battle.use_move(0, 1, &mut rng).unwrap();
+    assert_eq!(foe_attack(&battle), -1);
+    battle.battler1.current.borrow_mut().stat_changes[attack] = 6;
+    battle.drain_events();
+    battle.use_move(0, 3, &mut rng).unwrap();
+    assert_eq!(battle.battler1.current.borrow().stat_changes[attack], 6);
+    assert!(battle.drain_events().iter().any(|event| {
+        if let Event::StatChangeFailed { .. } = event { true } else { false }
+    }));
+}
+
+#[test]
+fn test_events() {
+    let team = vec![Arc::new(clefairy())];
+    let mut battle = seeded(&team, &team);
+    battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+    let events = battle.drain_events();
+    assert!(if let Event::Turn(1) = events[0] { true } else { false });
+    let used = events.iter().filter(|event| {
+        if let Event::MoveUsed { .. } = event { true } else { false }
+    }).count();
+    assert_eq!(used, 2);
+    assert!(battle.drain_events().is_empty());
+}
+
+#[test]
+fn test_replay() {
+    let team = vec![Arc::new(clefairy()), Arc::new(clefairy())];
+    let mut battle = SingleBattle::new_seeded(
+        &team, &team, Ruleset::standard(), 42);
+    let mut events = Vec::new();
+    while battle.outcome.is_none() {
+        for side in 0..2 {
+            if battle.battler(side).needs_replacement() {
+                battle.replace(side, 1).unwrap();
+            }
+        }
+        battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+        events.extend(battle.drain_events());
+    }
+    let replay = Replay::decode(&battle.replay().encode()).unwrap();
+    let replayed = replay.play().unwrap();
+    assert_eq!(replayed.outcome, battle.outcome);
+    assert_eq!(format!("{:?}", replayed.drain_events()),
+        format!("{:?}", events));
+}
+
+#[test]
+fn test_send() {
+    let workers = (0..4).map(|seed| {
+        let team = vec![Arc::new(clefairy())];
+        let mut battle = SingleBattle::new_seeded(
+            &team, &team, Ruleset::default(), seed);
+        thread::spawn(move || {
+            while battle.outcome.is_none() {
+                battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+            }
+            battle.outcome
+        })
+    }).collect::<Vec<_>>();
+    for worker in workers {
+        assert!(worker.join().unwrap().is_some());
+    }
+}
+
+#[test]
+fn test_decode() {
+    let encoded = clefairy().encode();
+    assert!(team::TeamMember::decode(&encoded).is_some());
+    let mut tokens = encoded.split_whitespace().collect::<Vec<_>>();
+    tokens[4] = "65535";
+    assert!(team::TeamMember::decode(&tokens.join(" ")).is_none());
+    let mut tokens = encoded.split_whitespace().collect::<Vec<_>>();
+    tokens[8] = "65535,-,-,-";
+    assert!(team::TeamMember::decode(&tokens.join(" ")).is_none());
+}
+
+fn scripted_client(
+    address: SocketAddr
+) -> thread::JoinHandle<Option<BattleOutcome>> {
+    thread::spawn(move || {
+        let mut client = Client::connect(address).unwrap();
+        let team = vec![Arc::new(clefairy()), Arc::new(clefairy())];
+        client.submit_team(&team).unwrap();
+        loop {
+            match client.receive().ok()? {
+                Update::TurnRequest(request) => {
+                    let option = request.usable_moves().next().unwrap();
+                    client.send_action(Action::Move(option.slot)).unwrap();
+                },
+                Update::Preview { picks, .. }
+                    => client.send_order((0..picks).rev().collect()).unwrap(),
+                Update::ReplaceRequest(switches)
+                    => client.send_replacement(switches[0]).unwrap(),
+                Update::End(outcome) => return Some(outcome),
+                _ => (),
+            }
+        }
+    })
+}
+
+#[test]
+fn test_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (first, _) = listener.accept().unwrap();
+        let (second, _) = listener.accept().unwrap();
+        let rules = Ruleset { team_preview: true, ..Default::default() };
+        server::serve_match(first, second, &rules).unwrap()
+    });
+    let clients = vec![scripted_client(address), scripted_client(address)];
+    let outcome = server.join().unwrap();
+    for client in clients {
+        assert_eq!(client.join().unwrap(), Some(outcome));
+    }
+}
+
+#[test]
+fn test_showdown() {
+    let text = "Clefairy (F)\nAbility: Magic Guard\nLevel: 5\n\
+        EVs: 0 HP\nLonely Nature\nIVs: 0 HP / 0 Atk / 0 Def / 0 SpA / 0 SpD / 0 Spe\n\
+        - Pound\n";
+    let team = showdown::import(text).unwrap();
+    let mut expected = clefairy();
+    expected.pp_ups = [showdown::DEFAULT_PP_UPS; 4];
+    assert_eq!(team[0].encode(), expected.encode());
+    let exported = showdown::export(&team);
+    assert_eq!(showdown::import(&exported).unwrap()[0].encode(), expected.encode());
+    let errors = showdown::import("Clefairy\n- Not A Move\nBogus").unwrap_err();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].line, 2);
+}
+
+#[test]
+fn test_names() {
+    let pokemon = &names().pokemon;
+    assert_eq!(pokemon[&normalize("Nidoran♀")], pokemon[&normalize("Nidoran-F")]);
+    assert_eq!(pokemon[&normalize("Nidoran♂")], pokemon[&normalize("Nidoran-M")]);
+    assert_ne!(pokemon["nidoranf"], pokemon["nidoranm"]);
+    let rotom = pokemon["rotom"];
+    let wash = pokemon["rotomwash"];
+    assert_ne!(wash, rotom);
+    assert_eq!(species_ref(wash).name, species_ref(rotom).name);
+    assert_eq!(pokemon_name(wash), "Rotom-Wash");
+    assert_eq!(pokemon_name(rotom), "Rotom");
+    assert_ne!(pokemon["deoxysattack"], pokemon["deoxys"]);
+    assert_eq!(pokemon["deoxysnormal"], pokemon["deoxys"]);
+    let text = "Rotom-Wash\nAbility: Levitate\n- Thunderbolt\n";
+    let team = showdown::import(text).unwrap();
+    assert_eq!(team[0].pokemon.id, wash);
+    assert!(showdown::export(&team).starts_with("Rotom-Wash"));
+}
+
+#[test]
+fn test_rules() {
+    let team = vec![Arc::new(clefairy()), Arc::new(clefairy())];
+    assert!(Ruleset::default().validate(&team, false).is_empty());
+    assert_eq!(Ruleset::default().validate(&Vec::new(), false),
+        vec![rules::RuleViolation::TeamTooSmall { size: 0 }]);
+    let empty = SingleBattle::new_validated(
+        &Vec::new(), &team, Ruleset::default(), SEED).err();
+    assert_eq!(empty,
+        Some(vec![rules::RuleViolation::TeamTooSmall { size: 0 }]));
+    assert!(SingleBattle::new_validated(
+        &team, &team, Ruleset::default(), SEED).is_ok());
+    assert_eq!(Ruleset::standard().validate(&team, false),
+        vec![rules::RuleViolation::SpeciesClause { index: 1 }]);
+    let capped = Ruleset { level_cap: 4, ..Default::default() };
+    assert_eq!(capped.validate(&team[..1].to_vec(), false),
+        vec![rules::RuleViolation::LevelAboveCap { index: 0, level: 5 }]);
+    let scaled = Ruleset { level_scale: Some(50), ..Default::default() };
+    let battle = SingleBattle::new_seeded(&team, &team, scaled, SEED);
+    assert_eq!(battle.team(0)[0].level, 50);
+}
+
+#[test]
+fn test_preview() {
+    let mut fast = clefairy();
+    fast.level = 50;
+    let team = vec![Arc::new(clefairy()), Arc::new(fast)];
+    let rules = Ruleset { picks: Some(1), ..Default::default() };
+    let mut preview = TeamPreview::new(&team, &team, rules);
+    assert_eq!(preview.revealed(0).len(), 2);
+    assert_eq!(preview.choose(0, vec![0, 1]),
+        Err(PreviewError::WrongCount { expected: 1 }));
+    assert_eq!(preview.choose(0, vec![2]), Err(PreviewError::InvalidIndex));
+    preview.choose(0, vec![1]).unwrap();
+    assert!(!preview.is_ready());
+    preview.choose(1, vec![0]).unwrap();
+    let battle = preview.start_seeded(0).unwrap();
+    assert_eq!(battle.team(0).len(), 1);
+    assert_eq!(battle.team(0)[0].level, 50);
+    assert_eq!(battle.team(1)[0].level, 5);
+}
+
+#[test]
+fn test_weather() {
+    let mut drizzle = clefairy();
+    drizzle.ability = Ability::Drizzle;
+    let mut plain = clefairy();
+    plain.ability = Ability::CuteCharm;
+    let team1 = vec![Arc::new(drizzle)];
+    let team2 = vec![Arc::new(plain)];
+    let mut battle = seeded(&team1, &team2);
+    without_criticals(&battle);
+    assert_eq!(battle.field.borrow().weather, Weather::Rain);
+    assert_eq!(battle.field.borrow().weather_turns, None);
+    battle.battler1.current.borrow().set_weather(Weather::Sandstorm, Some(2));
+    battle.drain_events();
+    battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+    let residuals = battle.drain_events().iter().filter(|event| {
+        if let Event::Residual { kind: residual::Residual::Weather, .. } = event {
+            true
+        } else {
+            false
+        }
+    }).count();
+    assert_eq!(residuals, 1);
+    battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+    assert_eq!(battle.field.borrow().weather, Weather::Clear);
+    assert!(battle.drain_events().iter().any(|event| {
+        if let Event::WeatherEnded { weather: Weather::Sandstorm } = event {
+            true
+        } else {
+            false
+        }
+    }));
+    let mut snow_cloak = clefairy();
+    snow_cloak.ability = Ability::SnowCloak;
+    let team = vec![Arc::new(snow_cloak)];
+    let battle = seeded(&team, &team2);
+    assert!(!Weather::Hail.damages(&battle.battler1.current.borrow()));
+    assert!(Weather::Hail.damages(&battle.battler2.current.borrow()));
+    assert_eq!(Weather::Sun.healing(301), 200);
+    assert_eq!(Weather::Clear.healing(301), 150);
+    assert_eq!(Weather::Rain.healing(301), 75);
+}
+
+#[test]
+fn test_side_conditions() {
+    let dex = vdex::pokedex();
+    let mut screener = clefairy();
+    screener.moves[1] = Some(&dex.moves[moves::MoveId(114)]);
+    let team = vec![Arc::new(screener)];
+    let mut battle = seeded(&team, &team);
+    without_criticals(&battle);
+    battle.run_turn(Action::Move(1), Action::Move(0)).unwrap();
+    assert!(battle.field.borrow().has(0, SideCondition::Reflect));
+    assert!(!battle.field.borrow().has(1, SideCondition::Reflect));
+    for _ in 0..4 {
+        battle.run_turn(Action::Move(1), Action::Move(0)).unwrap();
+    }
+    assert!(!battle.field.borrow().has(0, SideCondition::Reflect));
+    let failed = battle.drain_events().iter().filter(|event| {
+        if let Event::MoveFailed { .. } = event { true } else { false }
+    }).count();
+    assert_eq!(failed, 4);
+}
+
+#[test]
+fn test_hazards() {
+    let dex = vdex::pokedex();
+    let mut setter = clefairy();
+    setter.moves[1] = Some(&dex.moves[moves::MoveId(445)]);
+    let mut plain = clefairy();
+    plain.ability = Ability::CuteCharm;
+    let team1 = vec![Arc::new(setter)];
+    let team2 = vec![Arc::new(plain), Arc::new(plain)];
+    let mut battle = seeded(&team1, &team2);
+    without_criticals(&battle);
+    battle.run_turn(Action::Move(1), Action::Move(0)).unwrap();
+    assert_eq!(battle.field.borrow().layers(1, Hazard::StealthRock), 1);
+    battle.drain_events();
+    battle.run_turn(Action::Move(1), Action::Switch(1)).unwrap();
+    let events = battle.drain_events();
+    assert!(events.iter().any(|event| {
+        if let Event::Hazard { hazard: Hazard::StealthRock, .. } = event {
+            true
+        } else {
+            false
+        }
+    }));
+    let perm = battle.battler2.bench[1].borrow();
+    assert!(perm.hp < perm.base.stat(vdex::Stat::HP));
+}
+
+#[test]
+fn test_abilities() {
+    let dex = vdex::pokedex();
+    let pound = &dex.moves[moves::MoveId(0)];
+    let member = |ability| {
+        let mut member = clefairy();
+        member.ability = ability;
+        member.level = 50;
+        Arc::new(member)
+    };
+    let team1 = vec![member(Ability::CuteCharm), member(Ability::Hustle),
+        member(Ability::HugePower)];
+    let team2 = vec![member(Ability::ShellArmor), member(Ability::CuteCharm)];
+    let mut battle = seeded(&team1, &team2);
+    let pound_with = |battle: &SingleBattle, critical| {
+        let mut ctx = DamageContext::new_basic(&battle.battler1.current,
+            &battle.battler2.current, 0, pound, 1, &mut battle.rng.clone());
+        ctx.critical = critical;
+        ctx
+    };
+    assert!(!pound_with(&battle, true).effective_critical());
+    battle.battler2.switch(1).unwrap();
+    assert!(pound_with(&battle, true).effective_critical());
+    let plain = pound_with(&battle, false);
+    let plain_damage = plain.calc_max_damage();
+    assert_eq!(plain.accuracy(), 1.0);
+    battle.battler1.switch(1).unwrap();
+    let hustle = pound_with(&battle, false);
+    let hustle_damage = hustle.calc_max_damage();
+    assert!(hustle_damage > plain_damage);
+    assert!((hustle.accuracy() - 0.8).abs() < 1e-9);
+    battle.battler1.switch(2).unwrap();
+    let huge_power = pound_with(&battle, false);
+    assert!(huge_power.calc_max_damage() > hustle_damage);
+    assert_eq!(huge_power.accuracy(), 1.0);
+}
+
+fn holding(name: &str) -> team::TeamMember {
+    let dex = vdex::pokedex();
+    let mut member = clefairy();
+    member.held = Some(&dex.items[names().items[name]]);
+    member.moves[1] = Some(&dex.moves[moves::MoveId(1)]);
+    member
+}
+
+#[test]
+fn test_items() {
+    let team1 = vec![Arc::new(holding("choicescarf"))];
+    let team2 = vec![Arc::new(holding("leftovers"))];
+    let mut battle = seeded(&team1, &team2);
+    without_criticals(&battle);
+    {
+        let scarfed = battle.battler1.current.borrow();
+        let other = battle.battler2.current.borrow();
+        assert!(scarfed.held_item().map_or(false, |held| held.is_choice()));
+        if let Some(HeldItem::Leftovers) = other.held_item() {} else {
+            panic!("Leftovers not recognized");
+        }
+        assert!(scarfed.speed() > other.speed());
+    }
+    battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+    assert_eq!(battle.battler1.check_action(Action::Move(1)),
+        Err(ActionError::ChoiceLocked));
+    assert_eq!(battle.battler2.check_action(Action::Move(1)), Ok(()));
+    assert!(battle.log.borrow().iter().any(|event| if let Event::Residual {
+        kind: residual::Residual::HeldItem, ..
+    } = event { true } else { false }));
+}
+
+#[test]
+fn test_life_orb() {
+    let dex = vdex::pokedex();
+    let mut holder = holding("lifeorb");
+    holder.ability = Ability::CuteCharm;
+    holder.moves[2] = Some(&dex.moves[moves::MoveId(23)]);
+    let team1 = vec![Arc::new(holder)];
+    let team2 = vec![Arc::new(clefairy())];
+    let battle = seeded(&team1, &team2);
+    let user = &battle.battler1.current;
+    let max_hp = user.borrow().overlay.stat(vdex::Stat::HP);
+    assert_eq!(battle.use_move(0, 2, &mut battle.rng.clone()), Ok(true));
+    assert_eq!(user.borrow().perm.borrow().hp, max_hp - (max_hp / 10).max(1));
+}
+
+#[test]
+fn test_berries() {
+    let team = vec![Arc::new(holding("lumberry"))];
+    let battle = seeded(&team, &team);
+    let mut current = battle.battler1.current.borrow_mut();
+    assert!(current.inflict(ailments::BenchAilment::Paralyzed));
+    assert!(!current.is_paralyzed());
+    assert!(current.held_item().is_none());
+    assert!(current.perm.borrow().consumed.is_some());
+    assert!(current.recycle());
+    assert!(current.held_item().is_some());
+    assert!(!current.recycle());
+    assert!(current.remove_item().is_some());
+    assert!(!current.recycle());
+}
+
+#[test]
+fn test_item_moves() {
+    let dex = vdex::pokedex();
+    let team1 = vec![Arc::new(holding("scopelens"))];
+    let team2 = vec![Arc::new(clefairy())];
+    let battle = seeded(&team1, &team2);
+    let user = &battle.battler1.current;
+    let target = &battle.battler2.current;
+    assert_eq!(user.borrow().critical_rate, 1);
+    assert!(user.borrow_mut().embargo(items::EMBARGO_TURNS));
+    assert!(user.borrow().held_item().is_none());
+    assert_eq!(user.borrow().critical_rate, 0);
+    for _ in 0..items::EMBARGO_TURNS {
+        user.borrow_mut().tick_embargo();
+    }
+    assert_eq!(user.borrow().critical_rate, 1);
+    let trick = &dex.moves[items::ITEM_SWAPPING_MOVES[0]];
+    assert!(execute_move(user, 4, trick,
+        |_| vec![target.clone()], &mut battle.rng.clone()));
+    assert_eq!(user.borrow().critical_rate, 0);
+    assert_eq!(target.borrow().critical_rate, 1);
+    assert!(user.borrow().perm.borrow().held.is_none());
+}
+
+#[test]
+fn test_triggers() {
+    let mut intimidator = clefairy();
+    intimidator.ability = Ability::Intimidate;
+    let mut booster = clefairy();
+    booster.ability = Ability::SpeedBoost;
+    let team1 = vec![Arc::new(intimidator)];
+    let team2 = vec![Arc::new(booster)];
+    let mut battle = seeded(&team1, &team2);
+    let attack = vdex::Stat::Attack.repr() as usize;
+    let speed = vdex::Stat::Speed.repr() as usize;
+    assert_eq!(battle.battler2.current.borrow().stat_changes[attack], -1);
+    assert_eq!(battle.battler1.current.borrow().stat_changes[attack], 0);
+    battle.run_turn(Action::Move(0), Action::Move(0)).unwrap();
+    assert_eq!(battle.battler2.current.borrow().stat_changes[speed], 1);
+    assert_eq!(battle.battler1.current.borrow().stat_changes[speed], 0);
+}
+
+#[test]
+fn test_synchronize() {
+    let mut synchronizer = clefairy();
+    synchronizer.ability = Ability::Synchronize;
+    let team1 = vec![Arc::new(clefairy())];
+    let team2 = vec![Arc::new(synchronizer)];
+    let battle = seeded(&team1, &team2);
+    let user = &battle.battler1.current;
+    let target = &battle.battler2.current;
+    let toxic = ailments::BenchAilment::Poisoned { bad: true };
+    assert!(target.borrow_mut().inflict(toxic));
+    let mut rng = battle.rng.clone();
+    let mut ctx = hooks::TriggerContext::new(
+        hooks::Trigger::OnStatusInflicted, target, Some(user), &mut rng);
+    ctx.status = Some(toxic);
+    ctx.fire();
+    match user.borrow().perm.borrow().status {
+        ailments::BenchAilment::Poisoned { bad } => assert!(!bad),
+        _ => panic!("Synchronize should have poisoned the user"),
+    }
+}